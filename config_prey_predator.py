@@ -1,12 +1,28 @@
 ## Info:
 ## This file is a reference (and dummy) user config to use with performance tests
 
-import cadcad_rs, random
+import cadcad_rs, logging, random
+
+# cadcad_rs logs to the "cadcad_rs" logger, DEBUG: per-run configs and reports
+logging.basicConfig(level=logging.INFO, format="%(message)s")
 
 ##
 sim_config = {
     'T': 100_000,  # timesteps
     'N': 1,   # times the simulation will be run (Monte Carlo runs)
+    'M': {    # params, list values are swept (one param subset per item)
+        'MAX_PREYS': 3000,
+    },
+    # 'seed': 42,  # master seed, seeds `random` (and `numpy.random`) per run
+    # 'strict_state_keys': True,  # error on state keys without a state update fn, instead of carrying them forward
+    # 'record_stride': 100,  # record every 100th timestep only
+    # 'record_keys': ['preys'],  # record these state keys only (and the cadCAD keys)
+    # 'final_state_only': True,  # record the final state of every run only
+    # 'stop_conditions': {'preys extinct': lambda state, params: state['preys'] <= 0},  # stop a run early
+    # 'steady_state': {'tolerance': 1, 'window': 100},  # stop a run once nothing changes
+    # 'cadcad_signatures': True,  # cadCAD's (params, substep, state_history, previous_state[, policy_input]) fns
+    # 'history_window': 10,  # timesteps of state_history kept (default: all)
+    # 'debug': True,  # check that functions do not mutate the state (slow)
 }
 
 ##
@@ -18,49 +34,54 @@ init_state = {
     'predators':  200.0, # This is float just to test software
 }
 
-## Params
-MAX_PREYS = 3000
-
 ## Policies
-def prey_change_normal_conditions(state, y):
+def prey_change_normal_conditions(state, params):
     preys =  state['preys']    
     # Assuming: preys_change goes down with every iteration since
     # natural resources limits the number of preys to MAX_PREYS 
-    preys_change = random.randint(0, MAX_PREYS-preys) if preys < MAX_PREYS else 0
+    preys_change = random.randint(0, params['MAX_PREYS']-preys) if preys < params['MAX_PREYS'] else 0
     return ( "preys_change", preys_change )
 
-def prey_pandemic(state, y):
+def prey_pandemic(state, params):
     return ( "preys_change", random.randint(-800, -700) )
 
-def predator_change_normal_conditions(state, y):
+def predator_change_normal_conditions(state, params):
     return ( "predators_change", random.uniform(-10.0, 10.0) )
 
-policies = [
-    prey_change_normal_conditions,
-    prey_pandemic, # enable to test addable signals
-    predator_change_normal_conditions,
-]
 
 # SUFS/Mechanisms
-def update_prey(state, signals):
+def update_prey(state, signals, params):
     preys = state['preys'] + signals['preys_change']
     return ('preys', preys)
 
-def update_predator(state, signals):
+def update_predator(state, signals, params):
     predators = state['predators'] + signals['predators_change']
     return ('predators', predators)
 
-state_update_fns = [
-    update_prey,
-    update_predator
+## Partial state update blocks (each block is a substep)
+partial_state_update_blocks = [
+    {
+        'policies': [
+            prey_change_normal_conditions,
+            prey_pandemic, # enable to test addable signals
+            predator_change_normal_conditions,
+        ],
+        'variables': [
+            update_prey,
+            update_predator
+        ]
+    },
 ]
 
+# Dry run of one timestep, prints the problems found (e.g. a signal key typo)
+for issue in cadcad_rs.validate(sim_config, init_state, partial_state_update_blocks):
+    print(issue)
+
 result_data = cadcad_rs.run_simulation(
   "config from python",
   sim_config,
   init_state,
-  policies,
-  state_update_fns,
+  partial_state_update_blocks,
   print_trajectory
 )
 
@@ -1,13 +1,20 @@
 ## Info:
 ## This file is a reference (and dummy) user config to use with performance tests
 
-import cadcad_rs, random
+import cadcad_rs, logging, random
+
+# cadcad_rs logs to the "cadcad_rs" logger, DEBUG: per-run configs and reports
+logging.basicConfig(level=logging.INFO, format="%(message)s")
 
 ##
 sim_config = {
     # 'T': 100_000,  # timesteps
     'T': 10,  # timesteps
     'N': 1,   # times the simulation will be run (Monte Carlo runs)
+    'M': {    # params, list values are swept (one param subset per item)
+        'MAX_PREYS': 3000,
+    },
+    # 'seed': 42,  # master seed, seeds `random` (and `numpy.random`) per run
 }
 
 ##
@@ -34,49 +41,50 @@ init_state = {
     'predators':  200.0, # This is float just to test software
 }
 
-## Params
-MAX_PREYS = 3000
-
 ## Policies
-def prey_change_normal_conditions(state, y):
+def prey_change_normal_conditions(state, params):
     preys =  state['preys']
     # Assuming: preys_change goes down with every iteration since
     # natural resources limits the number of preys to MAX_PREYS 
-    preys_change = Preys(random.randint(0, MAX_PREYS-preys.population)) # if preys.population < MAX_PREYS else Preys(0)
+    preys_change = Preys(random.randint(0, params['MAX_PREYS']-preys.population)) # if preys.population < MAX_PREYS else Preys(0)
     return ( "preys_change", preys_change )
 
-def prey_pandemic(state, y):
+def prey_pandemic(state, params):
     return ( "preys_change", Preys(random.randint(-200, -100)) )
 
-def predator_change_normal_conditions(state, y):
+def predator_change_normal_conditions(state, params):
     return ( "predators_change", random.uniform(-10.0, 10.0) )
 
-policies = [
-    prey_change_normal_conditions,
-    prey_pandemic, # enable to test addable signals
-    predator_change_normal_conditions,
-]
 
 # SUFS/Mechanisms
-def update_prey(state, signals):
+def update_prey(state, signals, params):
     preys = state['preys'] + signals['preys_change']
     return ('preys', preys)
 
-def update_predator(state, signals):
+def update_predator(state, signals, params):
     predators = state['predators'] + signals['predators_change']
     return ('predators', predators)
 
-state_update_fns = [
-    update_prey,
-    update_predator
+## Partial state update blocks (each block is a substep)
+partial_state_update_blocks = [
+    {
+        'policies': [
+            prey_change_normal_conditions,
+            prey_pandemic, # enable to test addable signals
+            predator_change_normal_conditions,
+        ],
+        'variables': [
+            update_prey,
+            update_predator
+        ]
+    },
 ]
 
 result_data = cadcad_rs.run_simulation(
   "config from python",
   sim_config,
   init_state,
-  policies,
-  state_update_fns,
+  partial_state_update_blocks,
   print_trajectory
 )
 
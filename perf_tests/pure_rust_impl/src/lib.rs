@@ -1,44 +1,89 @@
-use std::{collections::{BTreeMap}, usize};
-use std::ops::Add;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::panic::AssertUnwindSafe;
+use std::ops::{Add, Mul};
+use std::time::{Duration, Instant};
+use rayon::prelude::*;
+use rand::SeedableRng;
 
-//// Improvements:
+// Improvements:
 // Todo: Pre-allocate memory before everything (e.g. n_run * timesteps * sizeof State)
 // Todo: Remove unnecessary "pub"s
 
 // State Value Type
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Value {
     I32(i32),
     F64(f64),
     USIZE(usize),
 }
 
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::I32(val) => write!(f, "{}", val),
+            Self::F64(val) => write!(f, "{}", val),
+            Self::USIZE(val) => write!(f, "{}", val),
+        }
+    }
+}
+
+impl Value {
+    fn to_f64(self) -> f64 {
+        match self {
+            Self::I32(val) => val as f64,
+            Self::F64(val) => val,
+            Self::USIZE(val) => val as f64,
+        }
+    }
+}
+
 impl Add for Value {
     type Output = Self;
     fn add(self, other: Self) -> Self {
-        return match self {
-            Self::I32(val) => {
-                match other {
-                    Self::I32(val_other) => Self::I32(val + val_other),
-                    Self::F64(_) => panic!("-- Cannot add different enum types"),
-                    Self::USIZE(_) => panic!("-- Cannot add different enum types"),
-                }
-            },
-            Self::F64(val) => {
-                match other {
-                    Self::I32(_) => panic!("-- Cannot add different enum types"),
-                    Self::F64(val_other) => Self::F64(val + val_other),
-                    Self::USIZE(_) => panic!("-- Cannot add different enum types"),
-                }
-            }
-            Self::USIZE(val) => {
-                match other {
-                    Self::I32(_) => panic!("-- Cannot add different enum types"),
-                    Self::F64(_) => panic!("-- Cannot add different enum types"),
-                    Self::USIZE(val_other) => Self::USIZE(val + val_other),
-                }
-            }            
-        };
+        Aggregation::Sum.apply(self, other).expect("-- Cannot add different enum types")
+    }
+}
+
+// How the signals of several policies with the same key are combined.
+// Value has no list variant, so there is no list concatenation here
+#[derive(Debug, Clone, Copy)]
+pub enum Aggregation {
+    Sum, // Default
+    Product,
+    Min,
+    Max,
+    LastWriterWins,
+    // Returns None if the values cannot be combined
+    Custom(fn(Value, Value) -> Option<Value>),
+}
+
+impl Aggregation {
+    // None if the values cannot be combined (e.g. different enum types)
+    pub fn apply(&self, a: Value, b: Value) -> Option<Value> {
+        match (self, a, b) {
+            (Self::LastWriterWins, _, b) => Some(b),
+            (Self::Custom(f), a, b) => f(a, b),
+            (_, Value::I32(a), Value::I32(b)) => Some(Value::I32(self.reduce(a, b))),
+            (_, Value::F64(a), Value::F64(b)) => Some(Value::F64(self.reduce(a, b))),
+            (_, Value::USIZE(a), Value::USIZE(b)) => Some(Value::USIZE(self.reduce(a, b))),
+            _ => None,
+        }
+    }
+
+    fn reduce<T: Add<Output = T> + Mul<Output = T> + PartialOrd>(&self, a: T, b: T) -> T {
+        match self {
+            Self::Sum => a + b,
+            Self::Product => a * b,
+            Self::Min => if b < a { b } else { a },
+            Self::Max => if b > a { b } else { a },
+            Self::LastWriterWins | Self::Custom(_) => unreachable!(),
+        }
     }
 }
 
@@ -46,14 +91,88 @@ impl Add for Value {
 // Todo: Consider HashMap later
 pub type State = BTreeMap<String, Value>;
 pub type Trajectory = Vec<State>;
-pub type UpdateFunc = fn(&State, &Signals) -> Update;
-pub type PolicyFunc = fn(&State) -> Signal;
+pub type UpdateFunc = fn(&State, &Signals, &Params) -> Update;
+pub type PolicyFunc = fn(&State, &mut Context) -> PolicySignals;
 pub type Signals = BTreeMap<String, Value>;
+pub type Params = BTreeMap<String, Value>;
+// Param values to sweep, e.g. { "max_preys": [3000, 4000] }
+pub type ParamSweep = BTreeMap<String, Vec<Value>>;
+// Portable RNG (same numbers for the same seed across rand versions/platforms)
+pub type SimRng = rand_chacha::ChaCha8Rng;
 
 #[derive(Debug)]
 pub struct SimConfig { 
     pub n_run: usize,
-    pub timesteps: usize
+    pub timesteps: usize,
+    pub params: ParamSweep, // cadCAD's "M"
+    pub seed: Option<u64>,  // Master seed, None: a random one
+    // Panic on state keys without a state update fn in a substep
+    pub strict_state_keys: bool,
+    // The recording options of the Python engine, see Recorder
+    pub record_stride: usize,
+    pub record_keys: Option<Vec<String>>,
+    pub final_state_only: bool,
+    // Runs stop once at steady state, None: no detection
+    pub steady_state: Option<SteadyState>,
+}
+
+// Detected as in the Python engine: window timesteps in a row without a
+// value of keys changing by more than tolerance
+#[derive(Debug, Clone)]
+pub struct SteadyState {
+    pub tolerance: f64,
+    pub window: usize,
+    pub keys: Option<Vec<String>>,
+}
+
+impl SteadyState {
+    fn is_unchanged(&self, previous: &State, current: &State) -> bool {
+        let is_unchanged = |key: &String| match (previous.get(key), current.get(key)) {
+            (Some(a), Some(b)) => (a.to_f64() - b.to_f64()).abs() <= self.tolerance,
+            _ => false,
+        };
+        match &self.keys {
+            Some(keys) => keys.iter().all(is_unchanged),
+            None => current.keys().filter(|key| !is_cadcad_key(key)).all(is_unchanged),
+        }
+    }
+}
+
+// Checked on the state at the end of every timestep, a run stops at the
+// first one it holds on. name is reported as the reason of the stop
+pub struct StopCondition<'a> {
+    pub name: &'static str,
+    pub predicate: &'a (dyn Fn(&State, &Params) -> bool + Sync),
+}
+
+// Why a run stopped before its last timestep
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    Condition(&'static str), // See StopCondition
+    SteadyState,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Condition(name) => write!(f, "{}", name),
+            Self::SteadyState => write!(f, "steady state"),
+        }
+    }
+}
+
+// Timestep a run stopped at, and why
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EarlyStop {
+    pub timestep: usize,
+    pub reason: StopReason,
+}
+
+// Passed to policies, rng is seeded per run (see run_seed). params are
+// owned once an intervention changed them
+pub struct Context<'a> {
+    pub params: Cow<'a, Params>,
+    pub rng: SimRng,
 }
 
 pub struct StateKeyAndUpdateFn {
@@ -61,6 +180,12 @@ pub struct StateKeyAndUpdateFn {
     pub update_func: UpdateFunc
 }
 
+// A substep, as in the Python engine
+pub struct PartialStateUpdateBlock<'a> {
+    pub policies: &'a [PolicyFunc],
+    pub variables: &'a [StateKeyAndUpdateFn],
+}
+
 #[derive(Debug)]
 pub struct Update {
     pub key: String,
@@ -73,90 +198,2327 @@ pub struct Signal {
     pub value: Value
 }
 
-#[allow(non_camel_case_types)]
-pub struct cadCADConfig<'a> {
-    pub name: String,
-    pub sim_config: SimConfig,
-    pub init_state: State,
-    pub policies: &'a [PolicyFunc],
-    pub state_key_and_update_fn_s: &'a [StateKeyAndUpdateFn],
-    pub print_trajectory: bool,
+// What a policy returns: no signal, one, or several (aggregated in order,
+// as those of several policies). Made with into() from a Signal, an
+// Option<Signal> or a Vec<Signal>, or collected from an iterator of them
+#[derive(Debug)]
+pub enum PolicySignals {
+    None,
+    One(Signal),
+    Many(Vec<Signal>),
 }
 
-fn print_trajectory(trajectory: &Trajectory) {
-    println!("--- Trajectory:");
-    for (i, state) in trajectory.iter().enumerate() {
-        println!("---   step {}: State {:?}", i, state);
+impl From<Signal> for PolicySignals {
+    fn from(signal: Signal) -> Self {
+        Self::One(signal)
     }
 }
 
-fn add_additional_init_state_keys(init_state: &mut State, i: usize) {
-    let _todo = init_state.insert("run".to_string(), Value::USIZE(i+1));
-    let _todo = init_state.insert("substep".to_string(), Value::USIZE(0));
-    let _todo = init_state.insert("timestep".to_string(), Value::USIZE(0));
+impl From<Option<Signal>> for PolicySignals {
+    fn from(signal: Option<Signal>) -> Self {
+        signal.map_or(Self::None, Self::One)
+    }
+}
+
+impl From<Vec<Signal>> for PolicySignals {
+    fn from(signals: Vec<Signal>) -> Self {
+        Self::Many(signals)
+    }
 }
 
-fn add_additional_new_state_keys(new_state: &mut State, i: usize, k: usize) {
-    let _todo = new_state.insert("run".to_string(), Value::USIZE(i+1));
-    let _todo = new_state.insert("substep".to_string(), Value::USIZE(1));
-    let _todo = new_state.insert("timestep".to_string(), Value::USIZE(k+1));
+impl std::iter::FromIterator<Signal> for PolicySignals {
+    fn from_iter<I: IntoIterator<Item = Signal>>(signals: I) -> Self {
+        Self::Many(signals.into_iter().collect())
+    }
 }
 
-pub fn run_simulation(cadcad_config: &cadCADConfig) {
-    // todo: create final_data - vec of traj.s
-    let sim_config = &cadcad_config.sim_config;
-    println!("----------------------------------------------");
-    println!("\n### Project: {} ...", &cadcad_config.name);
-    for i in 0..sim_config.n_run { // Simulation
-        println!("\n--- \n Starting simulation {} ...", i);
-        println!("---");
-        // 1. Display sim. config.
-        println!("--- SIM_CONFIG: {:?}", sim_config);
-
-        let now = std::time::Instant::now();
-        // 2. Create trajectory
-        let mut init_state = cadcad_config.init_state.clone();
-        add_additional_init_state_keys(&mut init_state, i);
-        let mut trajectory = vec![init_state];
-        for k in 0..sim_config.timesteps { // Experiment
-            let current_state = &trajectory[k];
-            let mut new_state = State::new();
+impl IntoIterator for PolicySignals {
+    type Item = Signal;
+    type IntoIter = std::iter::Chain<std::option::IntoIter<Signal>, std::vec::IntoIter<Signal>>;
+    // A single signal is not put in a Vec, so it is not allocated
+    fn into_iter(self) -> Self::IntoIter {
+        let (one, many) = match self {
+            Self::None => (None, Vec::new()),
+            Self::One(signal) => (Some(signal), Vec::new()),
+            Self::Many(signals) => (None, signals),
+        };
+        one.into_iter().chain(many)
+    }
+}
 
-            // a. Apply policies
-            let mut signals = Signals::new();
-            for policy in cadcad_config.policies {
-                let signal = policy(current_state);
-                if let Some(mut_sig) = signals.get_mut(&signal.key) {
-                    *mut_sig = *mut_sig + signal.value;
-                }                
-                else {
-                    signals.insert(signal.key, signal.value);
-                }
-            }
+// How Monte Carlo runs (of all param subsets) are executed
+#[derive(Debug, Clone, Copy)]
+pub enum ExecutionMode {
+    SingleThreaded,
+    // Runs spread across a pool of n_workers threads (0: one per CPU)
+    MultiThreaded { n_workers: usize },
+}
 
-            // b. Apply state update funcs
-            for key_and_update_fn in cadcad_config.state_key_and_update_fn_s {
-                let update = (key_and_update_fn.update_func)(current_state, &signals);
-                new_state.insert(update.key, update.value);
+// A finished run; seed is the run's own seed, see replay_run
+#[derive(Debug)]
+pub struct RunResult {
+    pub subset: usize,
+    pub run: usize,
+    pub seed: u64,
+    pub trajectory: Trajectory,
+    pub report: RunReport,
+}
+
+// The Python engine's run report, with memory_bytes an estimate of the
+// trajectory's heap and inline size
+#[derive(Debug, Clone, Default)]
+pub struct RunReport {
+    pub elapsed: Duration,
+    pub steps: usize,
+    pub states_recorded: usize,
+    pub memory_bytes: usize,
+    pub stop: Option<EarlyStop>,
+    pub interventions: Vec<Firing>,
+}
+
+impl fmt::Display for RunReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.2?}, {} steps, {} states recorded, ~{} bytes",
+            self.elapsed, self.steps, self.states_recorded, self.memory_bytes
+        )?;
+        if let Some(stop) = self.stop {
+            write!(f, ", stopped at timestep {} ({})", stop.timestep, stop.reason)?;
+        }
+        for firing in &self.interventions {
+            write!(f, "\n--- Intervention '{}' at timestep {}", firing.name, firing.timestep)?;
+        }
+        Ok(())
+    }
+}
+
+// ---- Logging: human-readable output through the log crate ----
+// debug: per run (config, params, seed, report), info: per simulation
+
+// Why a simulation could not run (config checks) or stopped. substep is
+// 1-based, as in the trajectory
+#[derive(Debug)]
+pub enum SimulationError {
+    // A state update fn declared for a key missing from init_state
+    UnknownStateKey { key: String, substep: usize },
+    // Two state update fns of the same substep declared for the same key
+    DuplicateStateKey { key: String, substep: usize },
+    // A state update fn returned an update of another key than its declared one
+    UpdateKeyMismatch { declared: String, returned: String, run: usize, timestep: usize, substep: usize },
+    // A state key without a state update fn, in strict_state_keys mode
+    MissingStateUpdateFn { key: String, run: usize, timestep: usize, substep: usize },
+    // Signals of the same key which their aggregation cannot combine
+    SignalAggregation { key: String, aggregation: Aggregation, values: (Value, Value), run: usize, timestep: usize, substep: usize },
+    // A sink file could not be created or written
+    Sink { path: PathBuf, error: io::Error },
+    // A checkpoint could not be written, read, or does not match the config
+    Checkpoint { path: PathBuf, error: io::Error },
+    // A param with neither 1 value nor as many as the longest param sweep
+    ParamSweep { key: String, len: usize, n_subset: usize },
+    // The threads of MultiThreaded mode could not be started
+    WorkerPool { n_workers: usize, error: rayon::ThreadPoolBuildError },
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownStateKey { key, substep } => write!(f,
+                "State update fn of substep {} declared for key '{}', which is not in init_state", substep, key
+            ),
+            Self::DuplicateStateKey { key, substep } => write!(f,
+                "Several state update fns of substep {} declared for key '{}'", substep, key
+            ),
+            Self::UpdateKeyMismatch { declared, returned, run, timestep, substep } => write!(f,
+                "State update fn declared for key '{}' returned an update of key '{}' (run {}, timestep {}, substep {})",
+                declared, returned, run, timestep, substep
+            ),
+            Self::MissingStateUpdateFn { key, run, timestep, substep } => write!(f,
+                "State key '{}' has no state update fn (run {}, timestep {}, substep {})", key, run, timestep, substep
+            ),
+            Self::SignalAggregation { key, aggregation, values, run, timestep, substep } => write!(f,
+                "Cannot aggregate signal '{}' with {:?}: {:?} and {:?} (run {}, timestep {}, substep {})",
+                key, aggregation, values.0, values.1, run, timestep, substep
+            ),
+            Self::Sink { path, error } => write!(f, "Cannot write sink {}: {}", path.display(), error),
+            Self::Checkpoint { path, error } => write!(f, "Cannot use checkpoint {}: {}", path.display(), error),
+            Self::ParamSweep { key, len, n_subset } => write!(f,
+                "Param '{}' has {} values, sweeps should have 1 or {} values", key, len, n_subset
+            ),
+            Self::WorkerPool { n_workers, error } => write!(f,
+                "Cannot start a pool of {} worker threads: {}", n_workers, error
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+// ---- Sinks: states written to a file as they are produced ----
+
+pub trait Sink: Send {
+    fn write_state(&mut self, state: &State) -> io::Result<()>;
+    // After the last state of a run, e.g. to end a Parquet row group
+    fn end_run(&mut self) -> io::Result<()> { Ok(()) }
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+// Columns of the first state
+pub struct CsvSink {
+    out: BufWriter<File>,
+    header: Option<Vec<String>>,
+}
+
+impl CsvSink {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self { out: BufWriter::new(File::create(path)?), header: None })
+    }
+}
+
+impl Sink for CsvSink {
+    fn write_state(&mut self, state: &State) -> io::Result<()> {
+        if self.header.is_none() {
+            let header: Vec<String> = state.keys().cloned().collect();
+            let fields: Vec<Cow<str>> = header.iter().map(|key| csv_field(key)).collect();
+            writeln!(self.out, "{}", fields.join(","))?;
+            self.header = Some(header);
+        }
+        let header = self.header.as_ref().unwrap();
+        if header.len() != state.len() || !header.iter().all(|key| state.contains_key(key)) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                "state keys {:?} differ from the CSV columns {:?}", state.keys().collect::<Vec<_>>(), header
+            )));
+        }
+        let row: Vec<String> = state.values().map(|value| value.to_string()).collect();
+        writeln!(self.out, "{}", row.join(","))
+    }
+
+    fn finish(&mut self) -> io::Result<()> { self.out.flush() }
+}
+
+// Quoted if needed (RFC 4180)
+fn csv_field(field: &str) -> Cow<'_, str> {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => Cow::Owned(format!("\"{}\"", field.replace('"', "\"\""))),
+        false => Cow::Borrowed(field),
+    }
+}
+
+pub struct JsonlSink {
+    out: BufWriter<File>,
+}
+
+impl JsonlSink {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self { out: BufWriter::new(File::create(path)?) })
+    }
+}
+
+impl Sink for JsonlSink {
+    fn write_state(&mut self, state: &State) -> io::Result<()> {
+        let fields: Vec<String> = state.iter().map(|(key, value)| match value {
+            Value::F64(val) if !val.is_finite() => format!("{}:null", json_string(key)),
+            _ => format!("{}:{}", json_string(key), value),
+        }).collect();
+        writeln!(self.out, "{{{}}}", fields.join(","))
+    }
+
+    fn finish(&mut self) -> io::Result<()> { self.out.flush() }
+}
+
+// JSON string (RFC 8259), non-ASCII characters are written as they are
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c < ' ' => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+// Parquet needs a newer toolchain than this crate's, so it is left to a
+// Custom sink (e.g. with the parquet crate, a row group per end_run)
+#[derive(Clone, Copy)]
+pub enum SinkFormat {
+    Csv,
+    Jsonl,
+    Custom(fn(&Path) -> io::Result<Box<dyn Sink>>),
+}
+
+pub struct SinkConfig {
+    // In MultiThreaded mode, one file per worker thread, e.g. out_0.csv
+    pub path: PathBuf,
+    pub format: SinkFormat,
+    // Keep the trajectories in memory (in the RunResults), else only the
+    // final state of a run is kept
+    pub keep_trajectories: bool,
+}
+
+// A sink, with what is needed to report its errors
+struct OpenSink {
+    path: PathBuf,
+    sink: Box<dyn Sink>,
+    keep_trajectories: bool,
+}
+
+impl OpenSink {
+    fn create(config: &SinkConfig, path: PathBuf) -> Result<Self, SimulationError> {
+        let sink: io::Result<Box<dyn Sink>> = match config.format {
+            SinkFormat::Csv => CsvSink::create(&path).map(|sink| Box::new(sink) as Box<dyn Sink>),
+            SinkFormat::Jsonl => JsonlSink::create(&path).map(|sink| Box::new(sink) as Box<dyn Sink>),
+            SinkFormat::Custom(create) => create(&path),
+        };
+        match sink {
+            Ok(sink) => Ok(Self { path, sink, keep_trajectories: config.keep_trajectories }),
+            Err(error) => Err(SimulationError::Sink { path, error }),
+        }
+    }
+
+    fn write_state(&mut self, state: &State) -> Result<(), SimulationError> {
+        let result = self.sink.write_state(state);
+        self.check(result)
+    }
+
+    fn end_run(&mut self) -> Result<(), SimulationError> {
+        let result = self.sink.end_run();
+        self.check(result)
+    }
+
+    fn finish(&mut self) -> Result<(), SimulationError> {
+        let result = self.sink.finish();
+        self.check(result)
+    }
+
+    fn check(&self, result: io::Result<()>) -> Result<(), SimulationError> {
+        result.map_err(|error| SimulationError::Sink { path: self.path.clone(), error })
+    }
+}
+
+// Sink file of worker thread t, e.g. out.csv -> out_0.csv
+fn worker_sink_path(path: &Path, t: usize) -> PathBuf {
+    let stem = path.file_stem().map_or("".into(), |stem| stem.to_string_lossy());
+    match path.extension() {
+        Some(ext) => path.with_file_name(format!("{}_{}.{}", stem, t, ext.to_string_lossy())),
+        None => path.with_file_name(format!("{}_{}", stem, t)),
+    }
+}
+
+// ---- Interventions: changes to runs, scheduled or triggered by their state ----
+
+// As in the Python engine, checked at the start of timesteps (1-based)
+pub enum Trigger<'a> {
+    At(usize),
+    Window(usize, usize), // Every timestep from the first to the second, included
+    // The first timestep the predicate holds on, only
+    When(&'a (dyn Fn(&State, &Params) -> bool + Sync)),
+}
+
+// Policies are switched off/on in every substep they are in, and their
+// state is kept by checkpoints
+pub enum Action {
+    SetState(&'static str, Value),
+    SetParam(&'static str, Value),
+    EnablePolicy(PolicyFunc),
+    DisablePolicy(PolicyFunc),
+}
+
+// Actions taken on a run when its trigger fires, name is reported in the
+// RunReport (see Firing)
+pub struct Intervention<'a> {
+    pub name: &'static str,
+    pub trigger: Trigger<'a>,
+    pub actions: &'a [Action],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Firing {
+    pub timestep: usize,
+    pub name: &'static str,
+}
+
+// What the interventions did to a run, besides its state and params
+#[derive(Default)]
+struct RunInterventions {
+    disabled_policies: BTreeSet<(usize, usize)>, // (substep, policy) indices
+    triggered: BTreeSet<usize>, // Interventions fired by a When trigger
+    firings: Vec<Firing>,
+}
+
+// Fires the interventions due at the start of timestep, in their order
+fn intervene(
+    cadcad_config: &cadCADConfig, timestep: usize, state: &mut State, context: &mut Context, run: &mut RunInterventions
+) {
+    for (n, intervention) in cadcad_config.interventions.iter().enumerate() {
+        let is_due = match intervention.trigger {
+            Trigger::At(t) => timestep == t,
+            Trigger::Window(from, to) => (from..=to).contains(&timestep),
+            Trigger::When(predicate) => !run.triggered.contains(&n) && predicate(state, &context.params),
+        };
+        if !is_due { continue; }
+        if let Trigger::When(_) = intervention.trigger { run.triggered.insert(n); }
+        for action in intervention.actions {
+            match *action {
+                Action::SetState(key, value) => { state.insert(key.to_string(), value); },
+                Action::SetParam(key, value) => { context.params.to_mut().insert(key.to_string(), value); },
+                Action::EnablePolicy(policy) | Action::DisablePolicy(policy) => {
+                    for (j, psub) in cadcad_config.partial_state_update_blocks.iter().enumerate() {
+                        // Fn pointers of this toolchain have no PartialEq
+                        let positions = psub.policies.iter().enumerate()
+                            .filter(|(_, f)| **f as usize == policy as usize)
+                            .map(|(p, _)| (j, p));
+                        for position in positions {
+                            match action {
+                                Action::DisablePolicy(_) => run.disabled_policies.insert(position),
+                                _ => run.disabled_policies.remove(&position),
+                            };
+                        }
+                    }
+                },
             }
-            add_additional_new_state_keys(&mut new_state, i, k);
+        }
+        run.firings.push(Firing { timestep, name: intervention.name });
+    }
+}
+
+// ---- Checkpoints: simulations resumed from disk, see resume_simulation ----
+
+// A checkpoint is files starting with the same header: CHECKPOINT_MAGIC,
+// the format version (u32) and the simulation (name, n_run, timesteps,
+// number of param subsets, master seed). The file at the config's path
+// then has the finished runs (subset, run index, seed, report, early stop,
+// firings, trajectory). Each run in progress has its own file, at the path
+// + ".run<subset>_<run index>", with its checkpoints (subset, run index,
+// seed, timestep reached, RNG word position (u128), states recorded,
+// timesteps at steady state, params, disabled policies, triggered
+// interventions, firings, current state, and the states recorded since
+// the previous checkpoint), removed once the run is done. Runs and
+// checkpoints are appended after their length in bytes, so that nothing is
+// written twice, and runs do not wait for each other's writes.
+// Early stops are a tag (0: none, 1: a stop condition, by name, 2: steady
+// state) then their timestep, firings an intervention name then a
+// timestep.
+// Numbers are little-endian, usizes written as u64s, strings and
+// collections prefixed by their length
+const CHECKPOINT_MAGIC: &[u8; 8] = b"CADCADCK";
+const CHECKPOINT_VERSION: u32 = 3;
+
+pub struct CheckpointConfig {
+    pub path: PathBuf,
+    // Timesteps between two checkpoints of a run in progress, runs are also
+    // checkpointed when done
+    pub every_timesteps: usize,
+}
+
+fn run_path(path: &Path, s: usize, i: usize) -> PathBuf {
+    path.with_file_name(format!(
+        "{}.run{}_{}", path.file_name().map_or("".into(), |name| name.to_string_lossy()), s, i
+    ))
+}
+
+fn checkpoint_header(cadcad_config: &cadCADConfig, n_subset: usize, master_seed: u64) -> Vec<u8> {
+    let mut header = CHECKPOINT_MAGIC.to_vec();
+    header.extend_from_slice(&CHECKPOINT_VERSION.to_le_bytes());
+    put_str(&mut header, &cadcad_config.name);
+    put_usize(&mut header, cadcad_config.sim_config.n_run);
+    put_usize(&mut header, cadcad_config.sim_config.timesteps);
+    put_usize(&mut header, n_subset);
+    put_u64(&mut header, master_seed);
+    header
+}
+
+fn put_u64(out: &mut Vec<u8>, n: u64) {
+    out.extend_from_slice(&n.to_le_bytes());
+}
+
+fn put_usize(out: &mut Vec<u8>, n: usize) {
+    put_u64(out, n as u64);
+}
+
+fn put_str(out: &mut Vec<u8>, s: &str) {
+    put_usize(out, s.len());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn put_state(out: &mut Vec<u8>, state: &State) {
+    put_usize(out, state.len());
+    for (key, value) in state {
+        put_str(out, key);
+        match value {
+            Value::I32(val) => { out.push(0); out.extend_from_slice(&val.to_le_bytes()); },
+            Value::F64(val) => { out.push(1); put_u64(out, val.to_bits()); },
+            Value::USIZE(val) => { out.push(2); put_usize(out, *val); },
+        }
+    }
+}
+
+fn put_trajectory(out: &mut Vec<u8>, trajectory: &[State]) {
+    put_usize(out, trajectory.len());
+    for state in trajectory { put_state(out, state); }
+}
+
+fn put_early_stop(out: &mut Vec<u8>, stop: Option<EarlyStop>) {
+    let stop = match stop {
+        Some(stop) => stop,
+        None => return out.push(0),
+    };
+    match stop.reason {
+        StopReason::Condition(name) => { out.push(1); put_str(out, name); },
+        StopReason::SteadyState => out.push(2),
+    }
+    put_usize(out, stop.timestep);
+}
+
+fn put_firings(out: &mut Vec<u8>, firings: &[Firing]) {
+    put_usize(out, firings.len());
+    for firing in firings {
+        put_str(out, firing.name);
+        put_usize(out, firing.timestep);
+    }
+}
+
+fn invalid_checkpoint(problem: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, problem)
+}
+
+// Reads what the put_* fns wrote
+struct CheckpointReader<'b> {
+    bytes: &'b [u8],
+}
+
+impl<'b> CheckpointReader<'b> {
+    fn take(&mut self, n: usize) -> io::Result<&'b [u8]> {
+        if self.bytes.len() < n { return Err(invalid_checkpoint("truncated file".to_string())); }
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn usize(&mut self) -> io::Result<usize> {
+        self.u64().map(|n| n as usize)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.usize()?;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|err| invalid_checkpoint(err.to_string()))
+    }
+
+    fn state(&mut self) -> io::Result<State> {
+        let mut state = State::new();
+        for _ in 0..self.usize()? {
+            let key = self.string()?;
+            let value = match self.take(1)?[0] {
+                0 => {
+                    let mut bytes = [0; 4];
+                    bytes.copy_from_slice(self.take(4)?);
+                    Value::I32(i32::from_le_bytes(bytes))
+                },
+                1 => Value::F64(f64::from_bits(self.u64()?)),
+                2 => Value::USIZE(self.usize()?),
+                tag => return Err(invalid_checkpoint(format!("unknown value tag {}", tag))),
+            };
+            state.insert(key, value);
+        }
+        Ok(state)
+    }
+
+    fn trajectory(&mut self) -> io::Result<Trajectory> {
+        (0..self.usize()?).map(|_| self.state()).collect()
+    }
+
+    // Stop conditions are found by name in the config
+    fn early_stop(&mut self, cadcad_config: &cadCADConfig) -> io::Result<Option<EarlyStop>> {
+        let reason = match self.take(1)?[0] {
+            0 => return Ok(None),
+            1 => {
+                let name = self.string()?;
+                let condition = cadcad_config.stop_conditions.iter().find(|condition| condition.name == name)
+                    .ok_or_else(|| invalid_checkpoint(format!("unknown stop condition '{}'", name)))?;
+                StopReason::Condition(condition.name)
+            },
+            2 => StopReason::SteadyState,
+            tag => return Err(invalid_checkpoint(format!("unknown early stop tag {}", tag))),
+        };
+        Ok(Some(EarlyStop { timestep: self.usize()?, reason }))
+    }
+
+    // Interventions are found by name in the config
+    fn firings(&mut self, cadcad_config: &cadCADConfig) -> io::Result<Vec<Firing>> {
+        (0..self.usize()?).map(|_| {
+            let name = self.string()?;
+            let intervention = cadcad_config.interventions.iter().find(|intervention| intervention.name == name)
+                .ok_or_else(|| invalid_checkpoint(format!("unknown intervention '{}'", name)))?;
+            Ok(Firing { name: intervention.name, timestep: self.usize()? })
+        }).collect()
+    }
 
-            trajectory.push(new_state);
+    // The master seed of a header of this simulation
+    fn header(&mut self, cadcad_config: &cadCADConfig, n_subset: usize) -> io::Result<u64> {
+        if self.take(8)? != CHECKPOINT_MAGIC {
+            return Err(invalid_checkpoint("not a checkpoint file".to_string()));
+        }
+        let mut version = [0; 4];
+        version.copy_from_slice(self.take(4)?);
+        let version = u32::from_le_bytes(version);
+        if version != CHECKPOINT_VERSION {
+            return Err(invalid_checkpoint(format!(
+                "format version {}, expected {}", version, CHECKPOINT_VERSION
+            )));
+        }
+        let sim_config = &cadcad_config.sim_config;
+        let simulation = (self.string()?, self.usize()?, self.usize()?, self.usize()?);
+        if simulation != (cadcad_config.name.clone(), sim_config.n_run, sim_config.timesteps, n_subset) {
+            return Err(invalid_checkpoint(format!(
+                "checkpoint of simulation '{}' ({} runs of {} timesteps, {} param subsets), not of this one",
+                simulation.0, simulation.1, simulation.2, simulation.3
+            )));
+        }
+        let master_seed = self.u64()?;
+        if sim_config.seed.map_or(false, |seed| seed != master_seed) {
+            return Err(invalid_checkpoint(format!("checkpoint of master seed {}", master_seed)));
         }
-        let elapsed = now.elapsed();
-        println!("--- End of simulation {:?}", i);
+        Ok(master_seed)
+    }
+
+    // The next record appended after its length, None at the end or if cut
+    // short (while being appended)
+    fn record(&mut self) -> io::Result<Option<CheckpointReader<'b>>> {
+        if self.bytes.len() < 8 { return Ok(None); }
+        let mut len = [0; 8];
+        len.copy_from_slice(&self.bytes[..8]);
+        let len = u64::from_le_bytes(len) as usize;
+        if self.bytes.len() - 8 < len { return Ok(None); }
+        self.take(8)?;
+        Ok(Some(CheckpointReader { bytes: self.take(len)? }))
+    }
 
-        // x. Stats
-        println!("--- Elapsed time: {:.2?}", elapsed);
-        let size_of_state = std::mem::size_of::<State>();
-        println!("--- Size of State obj.: {:?}", size_of_state);
-        println!("--- Size of traj. obj.: {}", std::mem::size_of_val(&*trajectory));
+    fn end(&self) -> io::Result<()> {
+        match self.bytes.is_empty() {
+            true => Ok(()),
+            false => Err(invalid_checkpoint("trailing bytes".to_string())),
+        }
+    }
 
-        // 3. Print trajectory
-        if cadcad_config.print_trajectory {
-            print_trajectory(&trajectory);
+    fn run_index(&mut self, cadcad_config: &cadCADConfig, n_subset: usize) -> io::Result<(usize, usize)> {
+        let (s, i) = (self.usize()?, self.usize()?);
+        match s < n_subset && i < cadcad_config.sim_config.n_run {
+            true => Ok((s, i)),
+            false => Err(invalid_checkpoint(format!("unknown run {} of param subset {}", i+1, s))),
         }
+    }
+
+    fn done_run(&mut self, cadcad_config: &cadCADConfig, n_subset: usize) -> io::Result<RunResult> {
+        let (s, i) = self.run_index(cadcad_config, n_subset)?;
+        let seed = self.u64()?;
+        let report = RunReport {
+            elapsed: Duration::from_nanos(self.u64()?),
+            steps: self.usize()?,
+            states_recorded: self.usize()?,
+            memory_bytes: self.usize()?,
+            stop: self.early_stop(cadcad_config)?,
+            interventions: self.firings(cadcad_config)?,
+        };
+        Ok(RunResult { subset: s, run: i+1, seed, trajectory: self.trajectory()?, report })
+    }
+
+    fn run_in_progress(
+        &mut self, cadcad_config: &cadCADConfig, n_subset: usize
+    ) -> io::Result<((usize, usize), RunCheckpoint)> {
+        let run = self.run_index(cadcad_config, n_subset)?;
+        let _seed = self.u64()?; // See run_seed
+        let timestep = self.usize()?;
+        let mut rng_word_pos = [0; 16];
+        rng_word_pos.copy_from_slice(self.take(16)?);
+        Ok((run, RunCheckpoint {
+            timestep,
+            rng_word_pos: u128::from_le_bytes(rng_word_pos),
+            states_recorded: self.usize()?,
+            steady_timesteps: self.usize()?,
+            params: self.state()?,
+            interventions: RunInterventions {
+                disabled_policies: (0..self.usize()?)
+                    .map(|_| Ok((self.usize()?, self.usize()?)))
+                    .collect::<io::Result<_>>()?,
+                triggered: (0..self.usize()?).map(|_| self.usize()).collect::<io::Result<_>>()?,
+                firings: self.firings(cadcad_config)?,
+            },
+            state: self.state()?,
+            trajectory: self.trajectory()?,
+        }))
+    }
+}
+
+// A run in progress, as of the end of a timestep. Read from its file, the
+// trajectory has the states recorded since the previous checkpoint
+struct RunCheckpoint {
+    timestep: usize,
+    rng_word_pos: u128,
+    states_recorded: usize,
+    steady_timesteps: usize,
+    params: Params,
+    interventions: RunInterventions,
+    state: State,
+    trajectory: Trajectory,
+}
+
+// The file of a run in progress, which its checkpoints are appended to
+struct RunFile {
+    path: PathBuf,
+    file: Option<File>, // Opened by its first checkpoint
+    is_resumed: bool,
+    states_saved: usize, // States of the trajectory in the file
+}
+
+// Writes the checkpoints of a simulation, and has the runs of the one it
+// resumes (taken by the runs as they start). Only the finished runs share
+// a file
+struct Checkpointer<'a> {
+    config: &'a CheckpointConfig,
+    header: Vec<u8>,
+    done: Mutex<File>,
+    resumed_done: Mutex<BTreeMap<(usize, usize), RunResult>>,
+    resumed_in_progress: Mutex<BTreeMap<(usize, usize), RunCheckpoint>>,
+}
+
+impl<'a> Checkpointer<'a> {
+    // Overwrites any checkpoint at config.path
+    fn create(
+        config: &'a CheckpointConfig, cadcad_config: &cadCADConfig, n_subset: usize, master_seed: u64
+    ) -> Result<Self, SimulationError> {
+        let header = checkpoint_header(cadcad_config, n_subset, master_seed);
+        let create_done = || -> io::Result<File> {
+            let mut done = File::create(&config.path)?;
+            done.write_all(&header)?;
+            done.sync_all()?;
+            // Runs in progress of the previous checkpoint
+            for s in 0..n_subset {
+                for i in 0..cadcad_config.sim_config.n_run {
+                    remove_if_exists(&run_path(&config.path, s, i))?;
+                }
+            }
+            Ok(done)
+        };
+        let done = create_done().map_err(|error| SimulationError::Checkpoint { path: config.path.clone(), error })?;
+        Ok(Checkpointer {
+            config,
+            header,
+            done: Mutex::new(done),
+            resumed_done: Mutex::default(),
+            resumed_in_progress: Mutex::default(),
+        })
+    }
 
+    // The checkpointer of the simulation checkpointed at config.path, and
+    // its master seed. A run or checkpoint cut short while being appended is
+    // dropped
+    fn load(
+        config: &'a CheckpointConfig, cadcad_config: &cadCADConfig, n_subset: usize
+    ) -> Result<(Self, u64), SimulationError> {
+        let error = |error| SimulationError::Checkpoint { path: config.path.clone(), error };
+        let bytes = std::fs::read(&config.path).map_err(error)?;
+        let mut reader = CheckpointReader { bytes: &bytes };
+        let master_seed = reader.header(cadcad_config, n_subset).map_err(error)?;
+        let header = bytes[..bytes.len() - reader.bytes.len()].to_vec();
+        let mut resumed_done = BTreeMap::new();
+        let mut read_done = || -> io::Result<File> {
+            while let Some(mut run) = reader.record()? {
+                let result = run.done_run(cadcad_config, n_subset)?;
+                run.end()?;
+                resumed_done.insert((result.subset, result.run - 1), result);
+            }
+            open_append(&config.path, (bytes.len() - reader.bytes.len()) as u64)
+        };
+        let done = read_done().map_err(error)?;
+
+        let mut resumed_in_progress = BTreeMap::new();
+        for s in 0..n_subset {
+            for i in 0..cadcad_config.sim_config.n_run {
+                let path = run_path(&config.path, s, i);
+                let run = match resumed_done.contains_key(&(s, i)) {
+                    // Interrupted between the end of the run and the
+                    // removal of its file
+                    true => remove_if_exists(&path).map(|_| None),
+                    false => Self::read_run(&path, &header, cadcad_config, n_subset, (s, i)),
+                };
+                if let Some(run) = run.map_err(|error| SimulationError::Checkpoint { path, error })? {
+                    resumed_in_progress.insert((s, i), run);
+                }
+            }
+        }
+        Ok((Checkpointer {
+            config,
+            header,
+            done: Mutex::new(done),
+            resumed_done: Mutex::new(resumed_done),
+            resumed_in_progress: Mutex::new(resumed_in_progress),
+        }, master_seed))
     }
-    println!("\n----------------------END---------------------\n");
-}
\ No newline at end of file
+
+    // The last checkpoint of run (s, i) in its file at path, if any, with
+    // the states of all its checkpoints. The file is cut after it, for the
+    // next ones to be appended
+    fn read_run(
+        path: &Path, header: &[u8], cadcad_config: &cadCADConfig, n_subset: usize, run: (usize, usize)
+    ) -> io::Result<Option<RunCheckpoint>> {
+        let bytes = match std::fs::read(path) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            bytes => bytes?,
+        };
+        // Interrupted while the header was written
+        if bytes.len() < header.len() && header.starts_with(&bytes) { return Ok(None); }
+        if !bytes.starts_with(header) {
+            return Err(invalid_checkpoint("run of another checkpoint".to_string()));
+        }
+        let mut reader = CheckpointReader { bytes: &bytes[header.len()..] };
+        let mut last: Option<RunCheckpoint> = None;
+        while let Some(mut record) = reader.record()? {
+            let (checkpointed, mut checkpoint) = record.run_in_progress(cadcad_config, n_subset)?;
+            record.end()?;
+            if checkpointed != run {
+                return Err(invalid_checkpoint(format!(
+                    "checkpoint of run {} of param subset {}", checkpointed.1 + 1, checkpointed.0
+                )));
+            }
+            if let Some(previous) = last {
+                checkpoint.trajectory = [previous.trajectory, checkpoint.trajectory].concat();
+            }
+            last = Some(checkpoint);
+        }
+        open_append(path, (bytes.len() - reader.bytes.len()) as u64)?;
+        Ok(last)
+    }
+
+    fn take_done(&self, s: usize, i: usize) -> Option<RunResult> {
+        self.resumed_done.lock().expect("-- Checkpoint of a panicked worker").remove(&(s, i))
+    }
+
+    fn take_in_progress(&self, s: usize, i: usize) -> Option<RunCheckpoint> {
+        self.resumed_in_progress.lock().expect("-- Checkpoint of a panicked worker").remove(&(s, i))
+    }
+
+    // The file of run i of param subset s, resumed from its checkpoint if any
+    fn run_file(&self, s: usize, i: usize, resumed: Option<&RunCheckpoint>) -> RunFile {
+        RunFile {
+            path: run_path(&self.config.path, s, i),
+            file: None,
+            is_resumed: resumed.is_some(),
+            states_saved: resumed.map_or(0, |run| run.trajectory.len()),
+        }
+    }
+
+    fn is_due(&self, timestep: usize, timesteps: usize) -> bool {
+        timestep % self.config.every_timesteps.max(1) == 0 && timestep < timesteps
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn save_run(
+        &self, run_file: &mut RunFile, (s, i): (usize, usize), seed: u64, timestep: usize, context: &Context,
+        recorder: &Recorder, steady_timesteps: usize, interventions: &RunInterventions, state: &State
+    ) -> Result<(), SimulationError> {
+        let mut run = vec![0; 8]; // Its length
+        put_usize(&mut run, s);
+        put_usize(&mut run, i);
+        put_u64(&mut run, seed);
+        put_usize(&mut run, timestep);
+        run.extend_from_slice(&context.rng.get_word_pos().to_le_bytes());
+        put_usize(&mut run, recorder.states_recorded);
+        put_usize(&mut run, steady_timesteps);
+        put_state(&mut run, &context.params);
+        put_usize(&mut run, interventions.disabled_policies.len());
+        for &(j, p) in &interventions.disabled_policies {
+            put_usize(&mut run, j);
+            put_usize(&mut run, p);
+        }
+        put_usize(&mut run, interventions.triggered.len());
+        for &n in &interventions.triggered { put_usize(&mut run, n); }
+        put_firings(&mut run, &interventions.firings);
+        put_state(&mut run, state);
+        put_trajectory(&mut run, &recorder.trajectory[run_file.states_saved..]);
+        let len = (run.len() - 8) as u64;
+        run[..8].copy_from_slice(&len.to_le_bytes());
+        let append = |run_file: &mut RunFile| -> io::Result<()> {
+            let file = match &mut run_file.file {
+                Some(file) => file,
+                None if run_file.is_resumed => {
+                    run_file.file.insert(std::fs::OpenOptions::new().append(true).open(&run_file.path)?)
+                },
+                None => {
+                    let file = run_file.file.insert(File::create(&run_file.path)?);
+                    file.write_all(&self.header)?;
+                    file
+                },
+            };
+            file.write_all(&run)?;
+            file.sync_data()
+        };
+        append(run_file).map_err(|error| SimulationError::Checkpoint { path: run_file.path.clone(), error })?;
+        run_file.states_saved = recorder.trajectory.len();
+        Ok(())
+    }
+
+    fn finish_run(&self, result: &RunResult) -> Result<(), SimulationError> {
+        let (s, i) = (result.subset, result.run - 1);
+        let mut run = vec![0; 8]; // Its length
+        put_usize(&mut run, s);
+        put_usize(&mut run, i);
+        put_u64(&mut run, result.seed);
+        put_u64(&mut run, result.report.elapsed.as_nanos() as u64);
+        put_usize(&mut run, result.report.steps);
+        put_usize(&mut run, result.report.states_recorded);
+        put_usize(&mut run, result.report.memory_bytes);
+        put_early_stop(&mut run, result.report.stop);
+        put_firings(&mut run, &result.report.interventions);
+        put_trajectory(&mut run, &result.trajectory);
+        let len = (run.len() - 8) as u64;
+        run[..8].copy_from_slice(&len.to_le_bytes());
+        let append = |done: &mut File| -> io::Result<()> {
+            done.write_all(&run)?;
+            done.sync_data()
+        };
+        append(&mut self.done.lock().expect("-- Checkpoint of a panicked worker"))
+            .map_err(|error| SimulationError::Checkpoint { path: self.config.path.clone(), error })?;
+        let path = run_path(&self.config.path, s, i);
+        remove_if_exists(&path).map_err(|error| SimulationError::Checkpoint { path, error })
+    }
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+// The file at path, cut to its first len bytes, opened to append to them
+fn open_append(path: &Path, len: u64) -> io::Result<File> {
+    let file = std::fs::OpenOptions::new().append(true).open(path)?;
+    file.set_len(len)?;
+    Ok(file)
+}
+
+#[allow(non_camel_case_types)]
+pub struct cadCADConfig<'a> {
+    pub name: String,
+    pub sim_config: SimConfig,
+    pub init_state: State,
+    pub partial_state_update_blocks: &'a [PartialStateUpdateBlock<'a>],
+    pub print_trajectory: bool,
+    pub execution_mode: ExecutionMode,
+    // Per signal key, keys not listed are summed
+    pub signal_aggregation: BTreeMap<String, Aggregation>,
+    pub sink: Option<SinkConfig>,
+    // Checkpoints written as runs go, see resume_simulation
+    pub checkpoint: Option<CheckpointConfig>,
+    pub stop_conditions: &'a [StopCondition<'a>],
+    pub interventions: &'a [Intervention<'a>],
+}
+
+// Not filtered by the logger's level
+fn print_trajectory(trajectory: &Trajectory) {
+    println!("--- Trajectory:");
+    for (i, state) in trajectory.iter().enumerate() {
+        println!("---   step {}: State {:?}", i, state);
+    }
+}
+
+// Estimate of the bytes taken by a state: its keys and values, not the
+// tree's nodes
+fn state_memory(state: &State) -> usize {
+    std::mem::size_of::<State>() + state.keys()
+        .map(|key| std::mem::size_of::<String>() + key.capacity() + std::mem::size_of::<Value>())
+        .sum::<usize>()
+}
+
+// Expands a param sweep into param subsets the same way cadCAD does: subset s
+// takes the s-th value of every param, params with a single value keep it
+// and sweeps of other lengths are an error
+pub fn param_subsets(sweep: &ParamSweep) -> Result<Vec<Params>, SimulationError> {
+    let n_subset = sweep.values().map(|values| values.len()).max().unwrap_or(1).max(1);
+    if let Some((key, values)) = sweep.iter().find(|(_, values)| values.len() > 1 && values.len() != n_subset) {
+        return Err(SimulationError::ParamSweep { key: key.clone(), len: values.len(), n_subset });
+    }
+    Ok((0..n_subset).map(|s| {
+        sweep.iter()
+            .filter_map(|(key, values)| {
+                values.get(s).or_else(|| values.first())
+                    .map(|value| (key.clone(), *value))
+            })
+            .collect()
+    }).collect())
+}
+
+fn splitmix64(z: u64) -> u64 {
+    let z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    let z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// The Python engine's run seeds: seeded runs are the same in every
+// execution mode
+pub fn run_seed(master_seed: u64, s: usize, i: usize) -> u64 {
+    splitmix64(splitmix64(master_seed.wrapping_add(s as u64)).wrapping_add(i as u64))
+}
+
+fn add_additional_init_state_keys(init_state: &mut State, s: usize, i: usize) {
+    let _todo = init_state.insert("subset".to_string(), Value::USIZE(s));
+    let _todo = init_state.insert("run".to_string(), Value::USIZE(i+1));
+    let _todo = init_state.insert("substep".to_string(), Value::USIZE(0));
+    let _todo = init_state.insert("timestep".to_string(), Value::USIZE(0));
+}
+
+// In place, new_state is a reused buffer (see run_single_simulation)
+fn add_additional_new_state_keys(new_state: &mut State, s: usize, i: usize, j: usize, k: usize) {
+    for (key, value) in CADCAD_KEYS.iter().zip([s, i+1, j+1, k+1]) {
+        match new_state.get_mut(*key) {
+            Some(old_value) => *old_value = Value::USIZE(value),
+            None => { new_state.insert(key.to_string(), Value::USIZE(value)); },
+        }
+    }
+}
+
+const CADCAD_KEYS: [&str; 4] = ["subset", "run", "substep", "timestep"];
+
+fn is_cadcad_key(key: &str) -> bool {
+    CADCAD_KEYS.contains(&key)
+}
+
+// Copies src into dst, only the values when both have the same keys (the
+// usual case) so that dst's nodes are reused
+fn copy_state(dst: &mut State, src: &State) {
+    if dst.len() == src.len() && dst.keys().eq(src.keys()) {
+        for (dst_value, src_value) in dst.values_mut().zip(src.values()) {
+            *dst_value = *src_value;
+        }
+    }
+    else {
+        dst.clone_from(src);
+    }
+}
+
+// Records the states of a run as per the SimConfig's recording options,
+// in its trajectory and sink
+struct Recorder<'a, 's> {
+    sim_config: &'a SimConfig,
+    sink: Option<&'s mut OpenSink>,
+    keep_trajectory: bool,
+    trajectory: Trajectory,
+    states_recorded: usize,
+}
+
+impl Recorder<'_, '_> {
+    fn is_recorded(&self, timestep: usize) -> bool {
+        !self.sim_config.final_state_only && timestep % self.sim_config.record_stride.max(1) == 0
+    }
+
+    fn record(&mut self, state: &State) -> Result<(), SimulationError> {
+        self.states_recorded += 1;
+        let recorded = match &self.sim_config.record_keys {
+            Some(keys) => state.iter()
+                .filter(|(key, _)| is_cadcad_key(key) || keys.contains(key))
+                .map(|(key, value)| (key.clone(), *value))
+                .collect(),
+            None if self.keep_trajectory => state.clone(),
+            // Nothing to allocate
+            None => {
+                if let Some(sink) = self.sink.as_mut() { sink.write_state(state)?; }
+                return Ok(());
+            },
+        };
+        if let Some(sink) = self.sink.as_mut() { sink.write_state(&recorded)?; }
+        if self.keep_trajectory { self.trajectory.push(recorded); }
+        Ok(())
+    }
+}
+
+// Config checks, before any run: state update fns are declared for keys
+// of init_state, one per key and substep
+pub fn check_state_keys(cadcad_config: &cadCADConfig) -> Result<(), SimulationError> {
+    match state_key_errors(cadcad_config).into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+fn state_key_errors(cadcad_config: &cadCADConfig) -> Vec<SimulationError> {
+    let mut errors = Vec::new();
+    for (j, psub) in cadcad_config.partial_state_update_blocks.iter().enumerate() {
+        let mut keys = BTreeSet::new();
+        for key_and_update_fn in psub.variables {
+            let key = key_and_update_fn.key;
+            if !cadcad_config.init_state.contains_key(key) {
+                errors.push(SimulationError::UnknownStateKey { key: key.to_string(), substep: j+1 });
+            }
+            if !keys.insert(key) {
+                errors.push(SimulationError::DuplicateStateKey { key: key.to_string(), substep: j+1 });
+            }
+        }
+    }
+    errors
+}
+
+// A problem found by validate, substep is 1-based (0: not of a substep)
+#[derive(Debug)]
+pub struct ValidationIssue {
+    pub substep: usize,
+    pub problem: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.substep {
+            0 => write!(f, "{}", self.problem),
+            substep => write!(f, "Substep {}: {}", substep, self.problem),
+        }
+    }
+}
+
+fn panic_message(panic: Box<dyn std::any::Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(msg) => *msg,
+        Err(panic) => panic.downcast_ref::<&str>().map_or("<no message>", |msg| msg).to_string(),
+    }
+}
+
+// Checks a config before running it: the state key and checkpoint checks of
+// run_simulation, then a dry run of each substep on the initial state (first param subset),
+// with panics of policies/state update fns caught and reported (they are
+// still printed by the panic hook, which is left as it is). Reports
+// signals no state update fn consumes (their removal changes no update) and
+// state updates changing the type of a value. A signal or state key read but
+// missing shows up as a panic ("key not found") of its reader
+pub fn validate(cadcad_config: &cadCADConfig) -> Vec<ValidationIssue> {
+    let mut issues: Vec<ValidationIssue> = state_key_errors(cadcad_config).into_iter().map(|err| {
+        let substep = match err {
+            SimulationError::UnknownStateKey { substep, .. } | SimulationError::DuplicateStateKey { substep, .. } => substep,
+            _ => 0,
+        };
+        ValidationIssue { substep, problem: err.to_string() }
+    }).collect();
+    if let Err(err) = check_checkpoint(cadcad_config) {
+        issues.push(ValidationIssue { substep: 0, problem: err.to_string() });
+    }
+
+    // First param subset, or the first values of a bad param sweep
+    let sweep = &cadcad_config.sim_config.params;
+    let params = &param_subsets(sweep).map(|subsets| subsets[0].clone()).unwrap_or_else(|err| {
+        issues.push(ValidationIssue { substep: 0, problem: err.to_string() });
+        sweep.iter().filter_map(|(key, values)| values.first().map(|value| (key.clone(), *value))).collect()
+    });
+    let mut context = Context { params: Cow::Borrowed(params), rng: SimRng::seed_from_u64(0) };
+    let mut state = cadcad_config.init_state.clone();
+    add_additional_init_state_keys(&mut state, 0, 0);
+    for (j, psub) in cadcad_config.partial_state_update_blocks.iter().enumerate() {
+        let mut issue = |problem: String| issues.push(ValidationIssue { substep: j+1, problem });
+
+        // a. Policies
+        let mut signals = Signals::new();
+        for (p, policy) in psub.policies.iter().enumerate() {
+            let policy_signals = match std::panic::catch_unwind(AssertUnwindSafe(|| policy(&state, &mut context))) {
+                Ok(policy_signals) => policy_signals,
+                Err(panic) => { issue(format!("Policy {} panicked: {}", p+1, panic_message(panic))); continue; },
+            };
+            for signal in policy_signals {
+                if let Some(mut_sig) = signals.get_mut(&signal.key) {
+                    let aggregation = cadcad_config.signal_aggregation
+                        .get(&signal.key).unwrap_or(&Aggregation::Sum);
+                    match aggregation.apply(*mut_sig, signal.value) {
+                        Some(value) => *mut_sig = value,
+                        None => issue(format!(
+                            "Policy {} returned a '{}' signal that cannot be aggregated with {:?}: {:?} and {:?}",
+                            p+1, signal.key, aggregation, mut_sig, signal.value
+                        )),
+                    }
+                }
+                else {
+                    signals.insert(signal.key, signal.value);
+                }
+            }
+        }
+
+        // b. State update fns
+        let call = |key_and_update_fn: &StateKeyAndUpdateFn, signals: &Signals| {
+            std::panic::catch_unwind(|| (key_and_update_fn.update_func)(&state, signals, params))
+        };
+        let mut new_state = state.clone();
+        let mut updates = Vec::new();
+        for key_and_update_fn in psub.variables {
+            let key = key_and_update_fn.key;
+            match call(key_and_update_fn, &signals) {
+                Ok(update) => {
+                    if update.key != key {
+                        issue(format!(
+                            "State update fn declared for key '{}' returned an update of key '{}'", key, update.key
+                        ));
+                    }
+                    if let Some(value) = state.get(&update.key) {
+                        if std::mem::discriminant(value) != std::mem::discriminant(&update.value) {
+                            issue(format!(
+                                "State update fn of key '{}' changes its type: {:?} to {:?}", key, value, update.value
+                            ));
+                        }
+                    }
+                    updates.push(Some(update.value));
+                    new_state.insert(update.key, update.value);
+                },
+                Err(panic) => {
+                    issue(format!("State update fn of key '{}' panicked: {}", key, panic_message(panic)));
+                    updates.push(None);
+                },
+            }
+        }
+
+        // c. Signals produced but not consumed
+        for signal_key in signals.keys() {
+            let mut other_signals = signals.clone();
+            other_signals.remove(signal_key);
+            let is_consumed = psub.variables.iter().zip(&updates).any(|(key_and_update_fn, value)| {
+                value.is_some() && call(key_and_update_fn, &other_signals).ok().map(|update| update.value) != *value
+            });
+            if !is_consumed {
+                issue(format!("Signal '{}' is not consumed by any state update fn", signal_key));
+            }
+        }
+
+        if cadcad_config.sim_config.strict_state_keys {
+            for key in state.keys().filter(|key| !is_cadcad_key(key)) {
+                if !psub.variables.iter().any(|key_and_update_fn| key_and_update_fn.key == key) {
+                    issue(format!("State key '{}' has no state update fn (strict_state_keys)", key));
+                }
+            }
+        }
+        add_additional_new_state_keys(&mut new_state, 0, 0, j, 0);
+        state = new_state;
+    }
+    issues
+}
+
+// Runs a single Monte Carlo run (i) of a param subset (s). The trajectory
+// has the recorded states (see Recorder), or only the final state when
+// the sink does not keep trajectories. With a checkpointer, the run goes
+// on from its checkpoint if it has one, and is checkpointed as it goes. A
+// run stopped early (see StopCondition and SteadyState) has a shorter
+// trajectory, which ends with the state it stopped at. Interventions fire
+// at the start of timesteps, their changes to the state show in the states
+// of the timestep
+fn run_single_simulation(
+    cadcad_config: &cadCADConfig, params: &Params, s: usize, i: usize, seed: u64,
+    sink: Option<&mut OpenSink>, checkpointer: Option<&Checkpointer>
+) -> Result<(Trajectory, RunReport), SimulationError> {
+    let now = Instant::now();
+    let sim_config = &cadcad_config.sim_config;
+    let keep_trajectory = sink.as_ref().map_or(true, |sink| sink.keep_trajectories);
+    let mut recorder = Recorder {
+        sim_config, sink, keep_trajectory, trajectory: Trajectory::new(), states_recorded: 0
+    };
+    let mut context = Context { params: Cow::Borrowed(params), rng: SimRng::seed_from_u64(seed) };
+    let mut steady_timesteps = 0;
+    let mut interventions = RunInterventions::default();
+    let resumed = checkpointer.and_then(|c| c.take_in_progress(s, i));
+    let mut run_file = checkpointer.map(|c| c.run_file(s, i, resumed.as_ref()));
+    let (mut current_state, first_timestep) = match resumed {
+        Some(run) => {
+            context.rng.set_word_pos(run.rng_word_pos);
+            if run.params != *params { context.params = Cow::Owned(run.params); }
+            interventions = run.interventions;
+            recorder.trajectory = run.trajectory;
+            recorder.states_recorded = run.states_recorded;
+            steady_timesteps = run.steady_timesteps;
+            (run.state, run.timestep)
+        },
+        None => {
+            let mut init_state = cadcad_config.init_state.clone();
+            add_additional_init_state_keys(&mut init_state, s, i);
+            if recorder.is_recorded(0) { recorder.record(&init_state)?; }
+            (init_state, 0)
+        },
+    };
+    // Swapped with current_state at each substep, so that states are only
+    // allocated when recorded
+    let mut new_state = State::new();
+    // State at the end of the previous timestep, for steady state detection
+    let mut previous_state = match sim_config.steady_state {
+        Some(_) => current_state.clone(),
+        None => State::new(),
+    };
+    let mut stop = None;
+    let mut last_timestep = sim_config.timesteps;
+    for k in first_timestep..sim_config.timesteps { // Experiment
+        intervene(cadcad_config, k+1, &mut current_state, &mut context, &mut interventions);
+        for (j, psub) in cadcad_config.partial_state_update_blocks.iter().enumerate() { // Substep
+            let current_state = &mut current_state;
+            // State keys without a state update fn are carried forward
+            match sim_config.strict_state_keys {
+                true => new_state.clear(),
+                false => copy_state(&mut new_state, current_state),
+            };
+
+            // a. Apply policies
+            let mut signals = Signals::new();
+            for (p, policy) in psub.policies.iter().enumerate() {
+                if interventions.disabled_policies.contains(&(j, p)) { continue; }
+                for signal in policy(current_state, &mut context) {
+                    if let Some(mut_sig) = signals.get_mut(&signal.key) {
+                        let aggregation = cadcad_config.signal_aggregation
+                            .get(&signal.key).unwrap_or(&Aggregation::Sum);
+                        *mut_sig = aggregation.apply(*mut_sig, signal.value).ok_or_else(|| {
+                            SimulationError::SignalAggregation {
+                                key: signal.key.clone(), aggregation: *aggregation, values: (*mut_sig, signal.value),
+                                run: i+1, timestep: k+1, substep: j+1,
+                            }
+                        })?;
+                    }
+                    else {
+                        signals.insert(signal.key, signal.value);
+                    }
+                }
+            }
+
+            // b. Apply state update funcs
+            for key_and_update_fn in psub.variables {
+                let update = (key_and_update_fn.update_func)(current_state, &signals, &context.params);
+                if update.key != key_and_update_fn.key {
+                    return Err(SimulationError::UpdateKeyMismatch {
+                        declared: key_and_update_fn.key.to_string(), returned: update.key,
+                        run: i+1, timestep: k+1, substep: j+1,
+                    });
+                }
+                new_state.insert(update.key, update.value);
+            }
+            if sim_config.strict_state_keys {
+                if let Some(key) = current_state.keys().find(|key| !is_cadcad_key(key) && !new_state.contains_key(*key)) {
+                    return Err(SimulationError::MissingStateUpdateFn {
+                        key: key.clone(), run: i+1, timestep: k+1, substep: j+1,
+                    });
+                }
+            }
+            add_additional_new_state_keys(&mut new_state, s, i, j, k);
+
+            std::mem::swap(current_state, &mut new_state);
+            if recorder.is_recorded(k+1) { recorder.record(current_state)?; }
+        }
+
+        // Early termination
+        let mut reason = cadcad_config.stop_conditions.iter()
+            .find(|condition| (condition.predicate)(&current_state, &context.params))
+            .map(|condition| StopReason::Condition(condition.name));
+        if let Some(steady_state) = &sim_config.steady_state {
+            steady_timesteps = match steady_state.is_unchanged(&previous_state, &current_state) {
+                true => steady_timesteps + 1,
+                false => 0,
+            };
+            if reason.is_none() && steady_timesteps >= steady_state.window.max(1) {
+                reason = Some(StopReason::SteadyState);
+            }
+            copy_state(&mut previous_state, &current_state);
+        }
+        if let Some(reason) = reason {
+            stop = Some(EarlyStop { timestep: k+1, reason });
+            last_timestep = k+1;
+            break;
+        }
+
+        if let (Some(checkpointer), Some(run_file)) = (checkpointer, &mut run_file) {
+            if checkpointer.is_due(k+1, sim_config.timesteps) {
+                checkpointer.save_run(
+                    run_file, (s, i), seed, k+1, &context, &recorder, steady_timesteps, &interventions, &current_state
+                )?;
+            }
+        }
+    }
+    // The state a run stopped at is recorded whatever the stride
+    if !recorder.is_recorded(last_timestep) && (sim_config.final_state_only || stop.is_some()) {
+        recorder.record(&current_state)?;
+    }
+    if let Some(sink) = recorder.sink { sink.end_run()?; }
+    let mut trajectory = recorder.trajectory;
+    if !keep_trajectory { trajectory.push(current_state); }
+    let report = RunReport {
+        elapsed: now.elapsed(),
+        steps: last_timestep * cadcad_config.partial_state_update_blocks.len(),
+        states_recorded: recorder.states_recorded,
+        memory_bytes: std::mem::size_of::<Trajectory>() + trajectory.iter().map(state_memory).sum::<usize>(),
+        stop,
+        interventions: interventions.firings,
+    };
+    Ok((trajectory, report))
+}
+
+fn log_run(cadcad_config: &cadCADConfig, params: &Params, result: &RunResult) {
+    log::debug!(
+        "--- Simulation {} of param subset {}: SIM_CONFIG: {:?}, params: {:?}, seed: {}\n--- Report: {}",
+        result.run, result.subset, cadcad_config.sim_config, params, result.seed, result.report
+    );
+    if cadcad_config.print_trajectory { print_trajectory(&result.trajectory); }
+}
+
+// Replays a single run of a simulation, given its RunResult's subset, run
+// and seed
+pub fn replay_run(
+    cadcad_config: &cadCADConfig, subset: usize, run: usize, seed: u64
+) -> Result<RunResult, SimulationError> {
+    check_state_keys(cadcad_config)?;
+    let params = &param_subsets(&cadcad_config.sim_config.params)?[subset];
+    let (trajectory, report) = run_single_simulation(cadcad_config, params, subset, run-1, seed, None, None)?;
+    Ok(RunResult { subset, run, seed, trajectory, report })
+}
+
+// Returns the runs ordered by (param subset, run), whatever the execution
+// mode is
+pub fn run_simulation(cadcad_config: &cadCADConfig) -> Result<Vec<RunResult>, SimulationError> {
+    check_state_keys(cadcad_config)?;
+    let master_seed = cadcad_config.sim_config.seed.unwrap_or_else(rand::random);
+    let n_subset = param_subsets(&cadcad_config.sim_config.params)?.len();
+    check_checkpoint(cadcad_config)?;
+    let checkpointer = cadcad_config.checkpoint.as_ref()
+        .map(|config| Checkpointer::create(config, cadcad_config, n_subset, master_seed))
+        .transpose()?;
+    simulate(cadcad_config, master_seed, checkpointer.as_ref())
+}
+
+// Resumes the simulation checkpointed at the config's checkpoint path: its
+// finished runs are returned as they are, its runs in progress go on from
+// their checkpoint and the others are run, so that the results are those
+// of an uninterrupted run_simulation. Checkpoints go on being written
+pub fn resume_simulation(cadcad_config: &cadCADConfig) -> Result<Vec<RunResult>, SimulationError> {
+    check_state_keys(cadcad_config)?;
+    let config = cadcad_config.checkpoint.as_ref().ok_or_else(|| SimulationError::Checkpoint {
+        path: PathBuf::new(),
+        error: io::Error::new(io::ErrorKind::InvalidInput, "no checkpoint config to resume from"),
+    })?;
+    check_checkpoint(cadcad_config)?;
+    let n_subset = param_subsets(&cadcad_config.sim_config.params)?.len();
+    let (checkpointer, master_seed) = Checkpointer::load(config, cadcad_config, n_subset)?;
+    if log::log_enabled!(log::Level::Info) {
+        log::info!("--- Resuming from {}: {} runs done, {} in progress",
+            config.path.display(),
+            checkpointer.resumed_done.lock().expect("-- Checkpoint of a panicked worker").len(),
+            checkpointer.resumed_in_progress.lock().expect("-- Checkpoint of a panicked worker").len()
+        );
+    }
+    simulate(cadcad_config, master_seed, Some(&checkpointer))
+}
+
+// States streamed to a sink after a checkpoint could not be taken back
+fn check_checkpoint(cadcad_config: &cadCADConfig) -> Result<(), SimulationError> {
+    match (&cadcad_config.checkpoint, &cadcad_config.sink) {
+        (Some(config), Some(_)) => Err(SimulationError::Checkpoint {
+            path: config.path.clone(),
+            error: io::Error::new(io::ErrorKind::InvalidInput, "checkpoints cannot be used with a sink"),
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn simulate(
+    cadcad_config: &cadCADConfig, master_seed: u64, checkpointer: Option<&Checkpointer>
+) -> Result<Vec<RunResult>, SimulationError> {
+    let sim_config = &cadcad_config.sim_config;
+    log::info!("### Project: {} ...\n--- Master seed: {}", cadcad_config.name, master_seed);
+
+    let subsets = param_subsets(&sim_config.params)?;
+    let runs: Vec<(usize, usize)> = (0..subsets.len())
+        .flat_map(|s| (0..sim_config.n_run).map(move |i| (s, i)))
+        .collect();
+    let run = |&(s, i): &(usize, usize), sink: Option<&mut OpenSink>| {
+        if let Some(result) = checkpointer.and_then(|c| c.take_done(s, i)) { return Ok(result); }
+        let seed = run_seed(master_seed, s, i);
+        let (trajectory, report) = run_single_simulation(
+            cadcad_config, &subsets[s], s, i, seed, sink, checkpointer
+        )?;
+        let result = RunResult { subset: s, run: i+1, seed, trajectory, report };
+        if let Some(checkpointer) = checkpointer { checkpointer.finish_run(&result)?; }
+        Ok(result)
+    };
+
+    let result_data = match cadcad_config.execution_mode {
+        ExecutionMode::SingleThreaded => {
+            let mut sink = match &cadcad_config.sink {
+                Some(config) => Some(OpenSink::create(config, config.path.clone())?),
+                None => None,
+            };
+            let results = runs.iter().map(|&(s, i)| {
+                let result = run(&(s, i), sink.as_mut())?;
+                log_run(cadcad_config, &subsets[s], &result);
+                Ok(result)
+            }).collect::<Result<Vec<_>, _>>();
+            // Finished whatever the outcome, with the states written so far
+            let finished = sink.as_mut().map_or(Ok(()), |sink| sink.finish());
+            let results = results?;
+            finished?;
+            results
+        },
+        ExecutionMode::MultiThreaded { n_workers } => {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(n_workers)
+                .build()
+                .map_err(|error| SimulationError::WorkerPool { n_workers, error })?;
+            // A sink per worker thread, created with its first run
+            let sinks: Vec<Mutex<Option<OpenSink>>> =
+                (0..pool.current_num_threads()).map(|_| Mutex::new(None)).collect();
+            // Indexed parallel iterator, so results are collected in run order
+            let results: Result<Vec<RunResult>, _> = pool.install(|| {
+                runs.par_iter().map(|run_index| {
+                    let config = match &cadcad_config.sink {
+                        Some(config) => config,
+                        None => return run(run_index, None),
+                    };
+                    let t = rayon::current_thread_index().unwrap_or(0);
+                    let mut sink = sinks[t].lock().expect("-- Sink of a panicked worker");
+                    if sink.is_none() {
+                        *sink = Some(OpenSink::create(config, worker_sink_path(&config.path, t))?);
+                    }
+                    run(run_index, sink.as_mut())
+                }).collect::<Result<_, _>>()
+            });
+            // As in SingleThreaded mode
+            let mut finished = Ok(());
+            for sink in sinks {
+                if let Some(mut sink) = sink.into_inner().expect("-- Sink of a panicked worker") {
+                    finished = finished.and(sink.finish());
+                }
+            }
+            let results = results?;
+            finished?;
+            for result in &results {
+                log_run(cadcad_config, &subsets[result.subset], result);
+            }
+            results
+        },
+    };
+    log::info!("### End of project: {}", cadcad_config.name);
+
+    Ok(result_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn noise(_state: &State, context: &mut Context) -> PolicySignals {
+        let noise = context.rng.gen_range(-1.0..1.0) * context.params["scale"].to_f64();
+        Signal { key: "noise".to_string(), value: Value::F64(noise) }.into()
+    }
+
+    fn update_x(state: &State, signals: &Signals, _params: &Params) -> Update {
+        Update { key: "x".to_string(), value: state["x"] + signals["noise"] }
+    }
+
+    fn sim_config(n_run: usize, timesteps: usize, seed: Option<u64>) -> SimConfig {
+        SimConfig {
+            n_run,
+            timesteps,
+            params: ParamSweep::from([("scale".to_string(), vec![Value::F64(1.0), Value::F64(2.0)])]),
+            seed,
+            strict_state_keys: false,
+            record_stride: 1,
+            record_keys: None,
+            final_state_only: false,
+            steady_state: None,
+        }
+    }
+
+    // Two substeps of a random walk of x
+    fn config(sim_config: SimConfig, execution_mode: ExecutionMode) -> cadCADConfig<'static> {
+        cadCADConfig {
+            name: "test".to_string(),
+            sim_config,
+            init_state: State::from([("x".to_string(), Value::F64(0.0))]),
+            partial_state_update_blocks: &[
+                PartialStateUpdateBlock {
+                    policies: &[noise],
+                    variables: &[StateKeyAndUpdateFn { key: "x", update_func: update_x }],
+                },
+                PartialStateUpdateBlock {
+                    policies: &[noise, noise],
+                    variables: &[StateKeyAndUpdateFn { key: "x", update_func: update_x }],
+                },
+            ],
+            print_trajectory: false,
+            execution_mode,
+            signal_aggregation: BTreeMap::new(),
+            sink: None,
+            checkpoint: None,
+            stop_conditions: &[],
+            interventions: &[],
+        }
+    }
+
+    fn trajectories(results: &[RunResult]) -> Vec<&Trajectory> {
+        results.iter().map(|result| &result.trajectory).collect()
+    }
+
+    #[test]
+    fn same_seed_same_trajectories_in_every_execution_mode() {
+        let run = |execution_mode| run_simulation(&config(sim_config(6, 50, Some(42)), execution_mode)).unwrap();
+        let single_threaded = run(ExecutionMode::SingleThreaded);
+        assert_eq!(trajectories(&single_threaded), trajectories(&run(ExecutionMode::SingleThreaded)));
+        for n_workers in [1, 3, 0] {
+            let multi_threaded = run(ExecutionMode::MultiThreaded { n_workers });
+            assert_eq!(trajectories(&single_threaded), trajectories(&multi_threaded));
+        }
+        // Run 4 of param subset 1
+        let result = &single_threaded[9];
+        let replayed = replay_run(&config(sim_config(6, 50, None), ExecutionMode::SingleThreaded), 1, 4, result.seed);
+        assert_eq!(replayed.unwrap().trajectory, result.trajectory);
+    }
+
+    #[test]
+    fn runs_get_different_random_streams() {
+        let results = run_simulation(&config(sim_config(6, 50, Some(42)), ExecutionMode::SingleThreaded)).unwrap();
+        let seeds: BTreeSet<u64> = results.iter().map(|result| result.seed).collect();
+        assert_eq!(seeds.len(), results.len());
+        let final_xs: Vec<f64> = results.iter().map(|result| result.trajectory.last().unwrap()["x"].to_f64()).collect();
+        for (n, x) in final_xs.iter().enumerate() {
+            assert!(final_xs[n+1..].iter().all(|other| other != x), "runs with the same stream: {:?}", final_xs);
+        }
+        let other_seed = run_simulation(&config(sim_config(6, 50, Some(43)), ExecutionMode::SingleThreaded)).unwrap();
+        assert_ne!(trajectories(&results), trajectories(&other_seed));
+    }
+
+    #[test]
+    fn multi_threaded_results_are_in_run_order() {
+        let results = run_simulation(&config(sim_config(16, 50, None), ExecutionMode::MultiThreaded { n_workers: 4 }))
+            .unwrap();
+        let order: Vec<(usize, usize)> = results.iter().map(|result| (result.subset, result.run)).collect();
+        let expected: Vec<(usize, usize)> = (0..2).flat_map(|s| (1..=16).map(move |run| (s, run))).collect();
+        assert_eq!(order, expected);
+        for result in &results {
+            let last = result.trajectory.last().unwrap();
+            assert_eq!((last["subset"], last["run"]), (Value::USIZE(result.subset), Value::USIZE(result.run)));
+        }
+    }
+
+    #[test]
+    fn aggregations() {
+        let (a, b) = (Value::I32(3), Value::I32(5));
+        assert_eq!(Aggregation::Sum.apply(a, b), Some(Value::I32(8)));
+        assert_eq!(Aggregation::Product.apply(a, b), Some(Value::I32(15)));
+        assert_eq!(Aggregation::Min.apply(b, a), Some(a));
+        assert_eq!(Aggregation::Max.apply(a, b), Some(b));
+        assert_eq!(Aggregation::Sum.apply(Value::F64(0.5), Value::F64(0.25)), Some(Value::F64(0.75)));
+        assert_eq!(Aggregation::Sum.apply(Value::USIZE(1), Value::USIZE(2)), Some(Value::USIZE(3)));
+        assert_eq!(Aggregation::LastWriterWins.apply(a, b), Some(b));
+        // Values of another type are replaced too
+        assert_eq!(Aggregation::LastWriterWins.apply(a, Value::F64(1.0)), Some(Value::F64(1.0)));
+        let mean = Aggregation::Custom(|a, b| Some(Value::F64((a.to_f64() + b.to_f64()) / 2.0)));
+        assert_eq!(mean.apply(a, Value::F64(4.0)), Some(Value::F64(3.5)));
+        assert_eq!(Aggregation::Custom(|_, _| None).apply(a, b), None);
+    }
+
+    #[test]
+    fn aggregations_of_different_types_fail() {
+        for aggregation in [Aggregation::Sum, Aggregation::Product, Aggregation::Min, Aggregation::Max] {
+            assert_eq!(aggregation.apply(Value::I32(1), Value::F64(1.0)), None);
+            assert_eq!(aggregation.apply(Value::F64(1.0), Value::USIZE(1)), None);
+        }
+    }
+
+    fn int_noise(_state: &State, context: &mut Context) -> PolicySignals {
+        Signal { key: "noise".to_string(), value: Value::I32(context.rng.gen_range(-1..1)) }.into()
+    }
+
+    #[test]
+    fn signals_of_different_types_are_a_simulation_error() {
+        let mut cadcad_config = config(sim_config(1, 5, None), ExecutionMode::SingleThreaded);
+        cadcad_config.partial_state_update_blocks = &[
+            PartialStateUpdateBlock {
+                policies: &[int_noise, noise],
+                variables: &[StateKeyAndUpdateFn { key: "x", update_func: update_x }],
+            },
+        ];
+        match run_simulation(&cadcad_config) {
+            Err(SimulationError::SignalAggregation {
+                key, values: (Value::I32(_), Value::F64(_)), timestep: 1, substep: 1, ..
+            }) => assert_eq!(key, "noise"),
+            other => panic!("expected a SignalAggregation error, got {:?}", other.map(|_| ())),
+        }
+        // Unless the last signal wins
+        cadcad_config.signal_aggregation.insert("noise".to_string(), Aggregation::LastWriterWins);
+        let results = run_simulation(&cadcad_config).unwrap();
+        assert!(results[0].trajectory.iter().all(|state| matches!(state["x"], Value::F64(_))));
+    }
+
+    fn update_y(state: &State, signals: &Signals, _params: &Params) -> Update {
+        Update { key: "y".to_string(), value: state["x"] + signals["noise"] }
+    }
+
+    #[test]
+    fn state_update_fns_of_keys_not_in_init_state_are_an_error() {
+        let mut cadcad_config = config(sim_config(1, 5, None), ExecutionMode::SingleThreaded);
+        cadcad_config.partial_state_update_blocks = &[
+            PartialStateUpdateBlock {
+                policies: &[noise],
+                variables: &[StateKeyAndUpdateFn { key: "y", update_func: update_y }],
+            },
+        ];
+        let err = run_simulation(&cadcad_config).unwrap_err();
+        assert!(matches!(&err, SimulationError::UnknownStateKey { key, substep: 1 } if key == "y"));
+        assert_eq!(err.to_string(), "State update fn of substep 1 declared for key 'y', which is not in init_state");
+    }
+
+    #[test]
+    fn state_update_fns_of_the_same_key_are_an_error() {
+        let mut cadcad_config = config(sim_config(1, 5, None), ExecutionMode::SingleThreaded);
+        cadcad_config.partial_state_update_blocks = &[
+            PartialStateUpdateBlock {
+                policies: &[noise],
+                variables: &[StateKeyAndUpdateFn { key: "x", update_func: update_x }],
+            },
+            PartialStateUpdateBlock {
+                policies: &[noise],
+                variables: &[
+                    StateKeyAndUpdateFn { key: "x", update_func: update_x },
+                    StateKeyAndUpdateFn { key: "x", update_func: update_x },
+                ],
+            },
+        ];
+        let err = run_simulation(&cadcad_config).unwrap_err();
+        assert!(matches!(&err, SimulationError::DuplicateStateKey { key, substep: 2 } if key == "x"));
+        assert_eq!(err.to_string(), "Several state update fns of substep 2 declared for key 'x'");
+    }
+
+    #[test]
+    fn updates_of_another_key_than_the_declared_one_are_an_error() {
+        let mut cadcad_config = config(sim_config(1, 5, None), ExecutionMode::SingleThreaded);
+        cadcad_config.partial_state_update_blocks = &[
+            PartialStateUpdateBlock {
+                policies: &[noise],
+                variables: &[StateKeyAndUpdateFn { key: "x", update_func: update_y }],
+            },
+        ];
+        let err = run_simulation(&cadcad_config).unwrap_err();
+        assert!(matches!(
+            &err,
+            SimulationError::UpdateKeyMismatch { declared, returned, run: 1, timestep: 1, substep: 1 }
+                if declared == "x" && returned == "y"
+        ));
+        assert_eq!(
+            err.to_string(),
+            "State update fn declared for key 'x' returned an update of key 'y' (run 1, timestep 1, substep 1)"
+        );
+    }
+
+    #[test]
+    fn state_keys_without_a_state_update_fn_are_carried_forward() {
+        let mut cadcad_config = config(sim_config(2, 5, Some(1)), ExecutionMode::SingleThreaded);
+        cadcad_config.init_state.insert("c".to_string(), Value::I32(7));
+        let results = run_simulation(&cadcad_config).unwrap();
+        for result in &results {
+            assert_eq!(result.trajectory.len(), 1 + 5 * 2);
+            assert!(result.trajectory.iter().all(|state| state["c"] == Value::I32(7)));
+        }
+    }
+
+    #[test]
+    fn state_keys_without_a_state_update_fn_are_an_error_in_strict_mode() {
+        let mut cadcad_config = config(sim_config(2, 5, Some(1)), ExecutionMode::SingleThreaded);
+        cadcad_config.init_state.insert("c".to_string(), Value::I32(7));
+        cadcad_config.sim_config.strict_state_keys = true;
+        let err = run_simulation(&cadcad_config).unwrap_err();
+        assert!(matches!(
+            &err, SimulationError::MissingStateUpdateFn { key, run: 1, timestep: 1, substep: 1 } if key == "c"
+        ));
+        assert_eq!(err.to_string(), "State key 'c' has no state update fn (run 1, timestep 1, substep 1)");
+        // Without it
+        cadcad_config.init_state.remove("c");
+        assert!(run_simulation(&cadcad_config).is_ok());
+    }
+
+    // (timestep, substep) of the states of a trajectory
+    fn steps(trajectory: &Trajectory) -> Vec<(usize, usize)> {
+        trajectory.iter().map(|state| (state["timestep"].to_f64() as usize, state["substep"].to_f64() as usize)).collect()
+    }
+
+    #[test]
+    fn recorder_keeps_every_record_stride_th_timestep() {
+        let full = run_simulation(&config(sim_config(2, 6, Some(1)), ExecutionMode::SingleThreaded)).unwrap();
+        let mut strided_config = sim_config(2, 6, Some(1));
+        strided_config.record_stride = 2;
+        let strided = run_simulation(&config(strided_config, ExecutionMode::SingleThreaded)).unwrap();
+        for (full, strided) in full.iter().zip(&strided) {
+            assert_eq!(steps(&strided.trajectory), [(0, 0), (2, 1), (2, 2), (4, 1), (4, 2), (6, 1), (6, 2)]);
+            let kept: Vec<&State> = full.trajectory.iter().filter(|state| state["timestep"].to_f64() as usize % 2 == 0).collect();
+            assert_eq!(strided.trajectory.iter().collect::<Vec<_>>(), kept);
+            assert_eq!((strided.report.steps, strided.report.states_recorded), (6 * 2, 7));
+        }
+    }
+
+    #[test]
+    fn recorder_keeps_the_record_keys_and_the_cadcad_keys() {
+        let mut cadcad_config = config(sim_config(1, 3, Some(1)), ExecutionMode::SingleThreaded);
+        let full = run_simulation(&cadcad_config).unwrap();
+        cadcad_config.sim_config.record_keys = Some(vec!["x".to_string()]);
+        assert_eq!(trajectories(&run_simulation(&cadcad_config).unwrap()), trajectories(&full));
+        cadcad_config.sim_config.record_keys = Some(Vec::new());
+        let results = run_simulation(&cadcad_config).unwrap();
+        for result in &results {
+            assert_eq!(result.trajectory.len(), 1 + 3 * 2);
+            assert!(result.trajectory.iter().all(|state| state.keys().eq(["run", "subset", "substep", "timestep"])));
+        }
+    }
+
+    #[test]
+    fn recorder_keeps_only_the_final_state() {
+        let full = run_simulation(&config(sim_config(2, 6, Some(1)), ExecutionMode::SingleThreaded)).unwrap();
+        let mut final_config = sim_config(2, 6, Some(1));
+        final_config.final_state_only = true;
+        let final_only = run_simulation(&config(final_config, ExecutionMode::SingleThreaded)).unwrap();
+        for (full, final_only) in full.iter().zip(&final_only) {
+            assert_eq!(final_only.trajectory, [full.trajectory.last().unwrap().clone()]);
+            assert_eq!((final_only.report.steps, final_only.report.states_recorded), (6 * 2, 1));
+        }
+    }
+
+    fn is_x_out_of_unit_interval(state: &State, _params: &Params) -> bool {
+        state["x"].to_f64().abs() > 1.0
+    }
+
+    #[test]
+    fn stop_conditions_end_runs_at_the_first_timestep_they_hold_on() {
+        let full = run_simulation(&config(sim_config(4, 50, Some(7)), ExecutionMode::SingleThreaded)).unwrap();
+        let conditions = [StopCondition { name: "x out of [-1, 1]", predicate: &is_x_out_of_unit_interval }];
+        let cadcad_config = cadCADConfig {
+            stop_conditions: &conditions,
+            ..config(sim_config(4, 50, Some(7)), ExecutionMode::SingleThreaded)
+        };
+        let stopped = run_simulation(&cadcad_config).unwrap();
+        let mut lengths = BTreeSet::new();
+        for (full, stopped) in full.iter().zip(&stopped) {
+            // The state at the end of timestep t is at 2 * t, after its 2 substeps
+            let timestep = (1..=50).find(|t| is_x_out_of_unit_interval(&full.trajectory[2 * t], &Params::new()))
+                .expect("x stays in [-1, 1]");
+            let stop = EarlyStop { timestep, reason: StopReason::Condition("x out of [-1, 1]") };
+            assert_eq!(stopped.report.stop, Some(stop));
+            assert_eq!(stopped.report.steps, timestep * 2);
+            assert_eq!(stopped.trajectory, full.trajectory[..=2 * timestep]);
+            lengths.insert(stopped.trajectory.len());
+        }
+        // Runs of different lengths
+        assert!(lengths.len() > 1);
+    }
+
+    #[test]
+    fn runs_stop_once_at_steady_state() {
+        let mut cadcad_config = config(sim_config(2, 10, Some(1)), ExecutionMode::SingleThreaded);
+        cadcad_config.init_state.insert("c".to_string(), Value::I32(7));
+        cadcad_config.sim_config.record_stride = 4;
+        cadcad_config.sim_config.steady_state = Some(SteadyState { tolerance: 0.0, window: 3, keys: Some(vec!["c".to_string()]) });
+        for result in run_simulation(&cadcad_config).unwrap() {
+            assert_eq!(result.report.stop, Some(EarlyStop { timestep: 3, reason: StopReason::SteadyState }));
+            // The state a run stopped at is recorded whatever the stride
+            assert_eq!(steps(&result.trajectory), [(0, 0), (3, 2)]);
+        }
+        // x, a random walk, is never steady
+        cadcad_config.sim_config.steady_state = Some(SteadyState { tolerance: 0.0, window: 3, keys: None });
+        assert!(run_simulation(&cadcad_config).unwrap().iter().all(|result| result.report.stop.is_none()));
+    }
+
+    fn step_by_scale(_state: &State, context: &mut Context) -> PolicySignals {
+        Signal { key: "step".to_string(), value: context.params["scale"] }.into()
+    }
+
+    // x stays put while step_by_scale is disabled
+    fn update_x_by_step(state: &State, signals: &Signals, _params: &Params) -> Update {
+        Update { key: "x".to_string(), value: state["x"] + signals.get("step").copied().unwrap_or(Value::F64(0.0)) }
+    }
+
+    fn is_x_at_least_103(state: &State, _params: &Params) -> bool {
+        state["x"].to_f64() >= 103.0
+    }
+
+    #[test]
+    fn interventions_fire_at_their_timesteps_and_once_when_triggered() {
+        let interventions = [
+            Intervention { name: "reset", trigger: Trigger::At(2), actions: &[Action::SetState("x", Value::F64(100.0))] },
+            Intervention { name: "pause", trigger: Trigger::Window(4, 5), actions: &[Action::DisablePolicy(step_by_scale)] },
+            Intervention { name: "resume", trigger: Trigger::At(6), actions: &[Action::EnablePolicy(step_by_scale)] },
+            Intervention {
+                name: "slow down", trigger: Trigger::When(&is_x_at_least_103), actions: &[Action::SetParam("scale", Value::F64(0.5))]
+            },
+        ];
+        let cadcad_config = cadCADConfig {
+            partial_state_update_blocks: &[PartialStateUpdateBlock {
+                policies: &[step_by_scale],
+                variables: &[StateKeyAndUpdateFn { key: "x", update_func: update_x_by_step }],
+            }],
+            interventions: &interventions,
+            ..config(sim_config(1, 8, Some(1)), ExecutionMode::SingleThreaded)
+        };
+        let results = run_simulation(&cadcad_config).unwrap();
+        let xs: Vec<Vec<f64>> = results.iter()
+            .map(|result| result.trajectory.iter().map(|state| state["x"].to_f64()).collect())
+            .collect();
+        // Scale 1.0 then 2.0: reset at 2, paused at 4 and 5, slowed down from 7 and 4
+        assert_eq!(xs, [
+            [0.0, 1.0, 101.0, 102.0, 102.0, 102.0, 103.0, 103.5, 104.0],
+            [0.0, 2.0, 102.0, 104.0, 104.0, 104.0, 104.5, 105.0, 105.5],
+        ]);
+        let firings = |result: &RunResult| -> Vec<(usize, &str)> {
+            result.report.interventions.iter().map(|firing| (firing.timestep, firing.name)).collect()
+        };
+        assert_eq!(firings(&results[0]), [(2, "reset"), (4, "pause"), (5, "pause"), (6, "resume"), (7, "slow down")]);
+        assert_eq!(firings(&results[1]), [(2, "reset"), (4, "pause"), (4, "slow down"), (5, "pause"), (6, "resume")]);
+    }
+
+    fn signal(value: f64) -> Signal {
+        Signal { key: "noise".to_string(), value: Value::F64(value) }
+    }
+
+    fn two_signals(_state: &State, _context: &mut Context) -> PolicySignals {
+        vec![signal(1.0), signal(2.0)].into()
+    }
+
+    fn no_signal(_state: &State, _context: &mut Context) -> PolicySignals {
+        Option::<Signal>::None.into()
+    }
+
+    fn collected_signals(_state: &State, _context: &mut Context) -> PolicySignals {
+        (1..=3).map(|n| signal(n as f64)).collect()
+    }
+
+    #[test]
+    fn policy_signals_of_every_shape_are_aggregated_in_order() {
+        let signals = |policy_signals: PolicySignals| -> Vec<f64> {
+            policy_signals.into_iter().map(|signal| signal.value.to_f64()).collect()
+        };
+        assert_eq!(signals(signal(1.0).into()), [1.0]);
+        assert_eq!(signals(Some(signal(1.0)).into()), [1.0]);
+        assert_eq!(signals(Option::<Signal>::None.into()), []);
+        assert_eq!(signals(vec![signal(1.0), signal(2.0)].into()), [1.0, 2.0]);
+
+        let x = |aggregation: Option<Aggregation>| {
+            let cadcad_config = cadCADConfig {
+                partial_state_update_blocks: &[PartialStateUpdateBlock {
+                    policies: &[two_signals, no_signal, collected_signals],
+                    variables: &[StateKeyAndUpdateFn { key: "x", update_func: update_x }],
+                }],
+                signal_aggregation: aggregation.into_iter().map(|aggregation| ("noise".to_string(), aggregation)).collect(),
+                ..config(sim_config(1, 1, Some(1)), ExecutionMode::SingleThreaded)
+            };
+            run_simulation(&cadcad_config).unwrap()[0].trajectory[1]["x"]
+        };
+        // 1, 2 then 1, 2, 3
+        assert_eq!(x(None), Value::F64(9.0));
+        assert_eq!(x(Some(Aggregation::Product)), Value::F64(12.0));
+        assert_eq!(x(Some(Aggregation::Max)), Value::F64(3.0));
+        assert_eq!(x(Some(Aggregation::Min)), Value::F64(1.0));
+    }
+
+    fn read_missing_state_key(state: &State, _context: &mut Context) -> PolicySignals {
+        Signal { key: "noise".to_string(), value: state["missing"] }.into()
+    }
+
+    fn unused_signal(_state: &State, _context: &mut Context) -> PolicySignals {
+        Signal { key: "unused".to_string(), value: Value::F64(1.0) }.into()
+    }
+
+    fn update_x_to_int(_state: &State, _signals: &Signals, _params: &Params) -> Update {
+        Update { key: "x".to_string(), value: Value::I32(1) }
+    }
+
+    fn validation_issues(
+        partial_state_update_blocks: &'static [PartialStateUpdateBlock<'static>], strict_state_keys: bool
+    ) -> Vec<String> {
+        let mut cadcad_config = config(sim_config(1, 5, None), ExecutionMode::SingleThreaded);
+        cadcad_config.partial_state_update_blocks = partial_state_update_blocks;
+        cadcad_config.sim_config.strict_state_keys = strict_state_keys;
+        validate(&cadcad_config).iter().map(|issue| issue.to_string()).collect()
+    }
+
+    #[test]
+    fn validate_finds_no_issue_in_a_valid_config() {
+        assert!(validate(&config(sim_config(1, 5, None), ExecutionMode::SingleThreaded)).is_empty());
+    }
+
+    #[test]
+    fn validate_reports_state_key_errors() {
+        let issues = validation_issues(&[
+            PartialStateUpdateBlock {
+                policies: &[noise],
+                variables: &[
+                    StateKeyAndUpdateFn { key: "x", update_func: update_x },
+                    StateKeyAndUpdateFn { key: "x", update_func: update_x },
+                    StateKeyAndUpdateFn { key: "y", update_func: update_y },
+                ],
+            },
+        ], false);
+        assert_eq!(issues, [
+            "Substep 1: Several state update fns of substep 1 declared for key 'x'",
+            "Substep 1: State update fn of substep 1 declared for key 'y', which is not in init_state",
+        ]);
+    }
+
+    #[test]
+    fn validate_reports_param_sweeps_of_different_lengths() {
+        let mut cadcad_config = config(sim_config(1, 5, None), ExecutionMode::SingleThreaded);
+        cadcad_config.sim_config.params.insert("other".to_string(), vec![Value::I32(1), Value::I32(2), Value::I32(3)]);
+        let issues: Vec<String> = validate(&cadcad_config).iter().map(|issue| issue.to_string()).collect();
+        assert_eq!(issues, ["Param 'scale' has 2 values, sweeps should have 1 or 3 values"]);
+    }
+
+    #[test]
+    fn validate_reports_checkpoints_with_a_sink() {
+        let mut cadcad_config = config(sim_config(1, 5, None), ExecutionMode::SingleThreaded);
+        cadcad_config.sink = Some(SinkConfig { path: "out.csv".into(), format: SinkFormat::Csv, keep_trajectories: false });
+        cadcad_config.checkpoint = Some(CheckpointConfig { path: "out.ckpt".into(), every_timesteps: 1 });
+        let issues: Vec<String> = validate(&cadcad_config).iter().map(|issue| issue.to_string()).collect();
+        assert_eq!(issues, ["Cannot use checkpoint out.ckpt: checkpoints cannot be used with a sink"]);
+    }
+
+    #[test]
+    fn validate_reports_panics() {
+        let issues = validation_issues(&[
+            PartialStateUpdateBlock {
+                policies: &[read_missing_state_key],
+                variables: &[StateKeyAndUpdateFn { key: "x", update_func: update_x }],
+            },
+        ], false);
+        assert_eq!(issues, [
+            "Substep 1: Policy 1 panicked: no entry found for key",
+            // No noise signal, as its policy panicked
+            "Substep 1: State update fn of key 'x' panicked: no entry found for key",
+        ]);
+    }
+
+    #[test]
+    fn validate_reports_signals_that_cannot_be_aggregated() {
+        let issues = validation_issues(&[
+            PartialStateUpdateBlock {
+                policies: &[noise, int_noise],
+                variables: &[StateKeyAndUpdateFn { key: "x", update_func: update_x }],
+            },
+        ], false);
+        assert_eq!(issues.len(), 1);
+        assert!(
+            issues[0].starts_with("Substep 1: Policy 2 returned a 'noise' signal that cannot be aggregated with Sum: F64("),
+            "{}", issues[0]
+        );
+    }
+
+    #[test]
+    fn validate_reports_signals_not_consumed() {
+        let issues = validation_issues(&[
+            PartialStateUpdateBlock {
+                policies: &[noise, unused_signal],
+                variables: &[StateKeyAndUpdateFn { key: "x", update_func: update_x }],
+            },
+        ], false);
+        assert_eq!(issues, ["Substep 1: Signal 'unused' is not consumed by any state update fn"]);
+    }
+
+    #[test]
+    fn validate_reports_bad_updates() {
+        let issues = validation_issues(&[
+            PartialStateUpdateBlock {
+                policies: &[],
+                variables: &[StateKeyAndUpdateFn { key: "x", update_func: update_x_to_int }],
+            },
+        ], false);
+        assert_eq!(issues, ["Substep 1: State update fn of key 'x' changes its type: F64(0.0) to I32(1)"]);
+        let issues = validation_issues(&[
+            PartialStateUpdateBlock {
+                policies: &[noise],
+                variables: &[StateKeyAndUpdateFn { key: "x", update_func: update_y }],
+            },
+        ], false);
+        assert_eq!(issues, ["Substep 1: State update fn declared for key 'x' returned an update of key 'y'"]);
+    }
+
+    #[test]
+    fn validate_reports_state_keys_without_a_state_update_fn_in_strict_mode() {
+        let blocks = &[
+            PartialStateUpdateBlock { policies: &[], variables: &[] },
+        ];
+        assert!(validation_issues(blocks, false).is_empty());
+        assert_eq!(validation_issues(blocks, true), ["Substep 1: State key 'x' has no state update fn (strict_state_keys)"]);
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_if_needed() {
+        assert_eq!(csv_field("preys"), "preys");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("a\nb"), "\"a\nb\"");
+    }
+
+    #[test]
+    fn json_strings_are_escaped() {
+        assert_eq!(json_string("preys"), "\"preys\"");
+        assert_eq!(json_string("é \"q\" \\ \n\t\u{1}"), "\"é \\\"q\\\" \\\\ \\n\\t\\u0001\"");
+    }
+
+    #[test]
+    fn param_subsets_take_the_s_th_value_of_sweeps() {
+        let sweep = ParamSweep::from([
+            ("a".to_string(), vec![Value::I32(1), Value::I32(2), Value::I32(3)]),
+            ("b".to_string(), vec![Value::F64(10.0), Value::F64(20.0), Value::F64(30.0)]),
+            ("c".to_string(), vec![Value::USIZE(7)]),
+        ]);
+        let subsets = param_subsets(&sweep).unwrap();
+        let expected = [
+            (Value::I32(1), Value::F64(10.0)),
+            (Value::I32(2), Value::F64(20.0)),
+            (Value::I32(3), Value::F64(30.0)),
+        ];
+        assert_eq!(subsets.len(), expected.len());
+        for (subset, (a, b)) in subsets.iter().zip(expected) {
+            assert_eq!(*subset, Params::from([
+                ("a".to_string(), a), ("b".to_string(), b), ("c".to_string(), Value::USIZE(7)),
+            ]));
+        }
+    }
+
+    #[test]
+    fn param_subsets_of_no_sweep() {
+        assert_eq!(param_subsets(&ParamSweep::new()).unwrap(), vec![Params::new()]);
+        let sweep = ParamSweep::from([("a".to_string(), vec![Value::I32(1)])]);
+        assert_eq!(param_subsets(&sweep).unwrap(), vec![Params::from([("a".to_string(), Value::I32(1))])]);
+    }
+
+    #[test]
+    fn param_sweeps_of_different_lengths_are_an_error() {
+        let sweep = ParamSweep::from([
+            ("a".to_string(), vec![Value::I32(1), Value::I32(2), Value::I32(3)]),
+            ("b".to_string(), vec![Value::F64(10.0), Value::F64(20.0)]),
+        ]);
+        let err = param_subsets(&sweep).unwrap_err();
+        assert!(matches!(&err, SimulationError::ParamSweep { key, len: 2, n_subset: 3 } if key == "b"));
+        assert_eq!(err.to_string(), "Param 'b' has 2 values, sweeps should have 1 or 3 values");
+    }
+
+    thread_local! {
+        static LOGGED: std::cell::RefCell<Vec<(log::Level, String)>> = std::cell::RefCell::new(Vec::new());
+    }
+
+    // Keeps the records of each test thread apart, tests run in parallel
+    struct TestLogger;
+
+    impl log::Log for TestLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            LOGGED.with(|logged| logged.borrow_mut().push((record.level(), record.args().to_string())));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static TEST_LOGGER: TestLogger = TestLogger;
+
+    #[test]
+    fn runs_are_reported_and_logged() {
+        let _already_set = log::set_logger(&TEST_LOGGER);
+        log::set_max_level(log::LevelFilter::Debug);
+        let results = run_simulation(&config(sim_config(2, 5, Some(1)), ExecutionMode::SingleThreaded)).unwrap();
+        for result in &results {
+            let report = &result.report;
+            assert_eq!((report.steps, report.states_recorded, report.stop), (5 * 2, 1 + 5 * 2, None));
+            assert!(report.memory_bytes >= result.trajectory.len() * std::mem::size_of::<Value>());
+        }
+        let logged = LOGGED.with(|logged| logged.take());
+        let info: Vec<&str> = logged.iter().filter(|(level, _)| *level == log::Level::Info).map(|(_, message)| message.as_str()).collect();
+        assert_eq!(info, ["### Project: test ...\n--- Master seed: 1", "### End of project: test"]);
+        let debug: Vec<&String> = logged.iter().filter(|(level, _)| *level == log::Level::Debug).map(|(_, message)| message).collect();
+        assert_eq!(debug.len(), 2 * 2);
+        assert!(debug[0].starts_with("--- Simulation 1 of param subset 0: SIM_CONFIG: "));
+        assert!(debug[0].ends_with(&format!("\n--- Report: {}", results[0].report)));
+    }
+
+    #[test]
+    fn run_reports_display() {
+        let report = RunReport {
+            elapsed: Duration::from_millis(1500),
+            steps: 10,
+            states_recorded: 6,
+            memory_bytes: 1024,
+            stop: Some(EarlyStop { timestep: 5, reason: StopReason::SteadyState }),
+            interventions: vec![Firing { timestep: 2, name: "lockdown" }],
+        };
+        assert_eq!(
+            report.to_string(),
+            "1.50s, 10 steps, 6 states recorded, ~1024 bytes, stopped at timestep 5 (steady state)\n\
+             --- Intervention 'lockdown' at timestep 2"
+        );
+    }
+
+    fn checkpoint_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("cadcad_rs_{}_{}.ckpt", name, std::process::id()))
+    }
+
+    // With the files of its runs in progress
+    fn remove_checkpoint(path: &Path) {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        for entry in std::fs::read_dir(path.parent().unwrap()).unwrap().flatten() {
+            if entry.file_name().to_string_lossy().starts_with(&name) {
+                let _todo = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    thread_local! {
+        // (subset, run, timestep) at which interruptible_noise fails the
+        // simulation
+        static INTERRUPT_AT: std::cell::Cell<Option<(usize, usize, usize)>> = std::cell::Cell::new(None);
+    }
+
+    // noise, unless interrupted: an I32 signal then fails the aggregation
+    fn interruptible_noise(state: &State, context: &mut Context) -> PolicySignals {
+        let at = (state["subset"], state["run"], state["timestep"]);
+        match INTERRUPT_AT.with(|interrupt_at| interrupt_at.get()) {
+            Some((s, run, k)) if at == (Value::USIZE(s), Value::USIZE(run), Value::USIZE(k)) => {
+                Signal { key: "noise".to_string(), value: Value::I32(0) }.into()
+            },
+            _ => noise(state, context),
+        }
+    }
+
+    #[test]
+    fn resumed_simulations_are_those_never_interrupted() {
+        let path = checkpoint_path("resume");
+        let mut cadcad_config = config(sim_config(3, 50, Some(42)), ExecutionMode::SingleThreaded);
+        cadcad_config.partial_state_update_blocks = &[
+            PartialStateUpdateBlock {
+                policies: &[noise, interruptible_noise],
+                variables: &[StateKeyAndUpdateFn { key: "x", update_func: update_x }],
+            },
+        ];
+        let uninterrupted = run_simulation(&cadcad_config).unwrap();
+
+        cadcad_config.checkpoint = Some(CheckpointConfig { path: path.clone(), every_timesteps: 10 });
+        INTERRUPT_AT.with(|interrupt_at| interrupt_at.set(Some((1, 2, 35))));
+        let interrupted = run_simulation(&cadcad_config);
+        INTERRUPT_AT.with(|interrupt_at| interrupt_at.set(None));
+        assert!(matches!(interrupted, Err(SimulationError::SignalAggregation { .. })));
+        // 4 runs done, run 2 of param subset 1 checkpointed at timestep 30
+        let (checkpointer, master_seed) = Checkpointer::load(cadcad_config.checkpoint.as_ref().unwrap(), &cadcad_config, 2)
+            .unwrap();
+        assert_eq!(master_seed, 42);
+        assert_eq!(checkpointer.resumed_done.lock().unwrap().keys().copied().collect::<Vec<_>>(),
+            [(0, 0), (0, 1), (0, 2), (1, 0)]);
+        assert_eq!(checkpointer.take_in_progress(1, 1).map(|run| run.timestep), Some(30));
+        drop(checkpointer);
+        // Only the run in progress has a file
+        assert!(run_path(&path, 1, 1).exists() && !run_path(&path, 1, 0).exists());
+        // Interrupted again while a finished run, and a checkpoint, were
+        // being appended
+        for path in [path.clone(), run_path(&path, 1, 1)] {
+            let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+            file.write_all(&[100, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3]).unwrap();
+        }
+
+        let resumed = resume_simulation(&cadcad_config).unwrap();
+        assert_eq!(trajectories(&resumed), trajectories(&uninterrupted));
+        let seeds = |results: &[RunResult]| results.iter().map(|result| result.seed).collect::<Vec<_>>();
+        assert_eq!(seeds(&resumed), seeds(&uninterrupted));
+        // The checkpoint of the resumed simulation has all its runs
+        assert!(!run_path(&path, 1, 1).exists());
+        let resumed_again = resume_simulation(&cadcad_config).unwrap();
+        assert_eq!(trajectories(&resumed_again), trajectories(&uninterrupted));
+        remove_checkpoint(&path);
+    }
+
+    #[test]
+    fn checkpoints_round_trip() {
+        let path = checkpoint_path("round_trip");
+        let mut cadcad_config = config(sim_config(2, 10, Some(7)), ExecutionMode::SingleThreaded);
+        cadcad_config.checkpoint = Some(CheckpointConfig { path: path.clone(), every_timesteps: 1 });
+        let checkpoint_config = cadcad_config.checkpoint.as_ref().unwrap();
+        let state = State::from([
+            ("i32".to_string(), Value::I32(i32::MIN)),
+            ("f64".to_string(), Value::F64(-1.5e-300)),
+            ("usize".to_string(), Value::USIZE(usize::MAX)),
+        ]);
+        let checkpointer = Checkpointer::create(checkpoint_config, &cadcad_config, 2, 7).unwrap();
+        let done = RunResult {
+            subset: 1,
+            run: 2,
+            seed: 3,
+            trajectory: vec![state.clone(), State::new()],
+            report: RunReport {
+                elapsed: Duration::from_nanos(123),
+                steps: 4,
+                states_recorded: 2,
+                memory_bytes: 5,
+                stop: Some(EarlyStop { timestep: 6, reason: StopReason::SteadyState }),
+                interventions: Vec::new(),
+            },
+        };
+        checkpointer.finish_run(&done).unwrap();
+        let params = Params::from([("scale".to_string(), Value::F64(2.0))]);
+        let mut context = Context { params: Cow::Borrowed(&params), rng: SimRng::seed_from_u64(8) };
+        context.rng.gen::<u64>();
+        let mut recorder = Recorder {
+            sim_config: &cadcad_config.sim_config,
+            sink: None,
+            keep_trajectory: true,
+            trajectory: vec![state.clone()],
+            states_recorded: 1,
+        };
+        let interventions = RunInterventions {
+            disabled_policies: BTreeSet::from([(1, 0)]),
+            triggered: BTreeSet::from([2]),
+            firings: Vec::new(),
+        };
+        let mut run_file = checkpointer.run_file(0, 1, None);
+        checkpointer.save_run(&mut run_file, (0, 1), 9, 4, &context, &recorder, 2, &interventions, &state).unwrap();
+        // The next checkpoint has the states recorded since
+        recorder.trajectory.push(State::new());
+        recorder.states_recorded = 2;
+        context.rng.gen::<u64>();
+        checkpointer.save_run(&mut run_file, (0, 1), 9, 5, &context, &recorder, 3, &interventions, &state).unwrap();
+        drop((checkpointer, run_file));
+
+        let (checkpointer, master_seed) = Checkpointer::load(checkpoint_config, &cadcad_config, 2).unwrap();
+        assert_eq!(master_seed, 7);
+        let loaded = checkpointer.take_done(1, 1).unwrap();
+        assert_eq!((loaded.subset, loaded.run, loaded.seed), (1, 2, 3));
+        assert_eq!(loaded.trajectory, done.trajectory);
+        assert_eq!(format!("{:?}", loaded.report), format!("{:?}", done.report));
+        let run = checkpointer.take_in_progress(0, 1).unwrap();
+        assert_eq!((run.timestep, run.rng_word_pos), (5, context.rng.get_word_pos()));
+        assert_eq!((run.states_recorded, run.steady_timesteps), (2, 3));
+        assert_eq!((run.params, run.state, run.trajectory), (params, state.clone(), vec![state, State::new()]));
+        assert_eq!(run.interventions.disabled_policies, interventions.disabled_policies);
+        assert_eq!(run.interventions.triggered, interventions.triggered);
+        assert!(checkpointer.take_done(0, 0).is_none() && checkpointer.take_in_progress(1, 1).is_none());
+
+        // Another simulation, magic or format version
+        let header = checkpoint_header(&cadcad_config, 2, 7);
+        let other_seed = checkpoint_header(&cadcad_config, 2, 8);
+        let mut bad_magic = header.clone();
+        bad_magic[..8].copy_from_slice(b"CADCADXX");
+        let mut old_version = header;
+        old_version[8..12].copy_from_slice(&1u32.to_le_bytes());
+        for (bytes, problem) in [
+            (other_seed, "checkpoint of master seed 8"),
+            (bad_magic, "not a checkpoint file"),
+            (old_version, "format version 1, expected 3"),
+        ] {
+            std::fs::write(&path, bytes).unwrap();
+            match Checkpointer::load(checkpoint_config, &cadcad_config, 2) {
+                Err(SimulationError::Checkpoint { error, .. }) => {
+                    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+                    assert_eq!(error.to_string(), problem);
+                },
+                _ => panic!("expected a Checkpoint error: {}", problem),
+            }
+        }
+        remove_checkpoint(&path);
+    }
+}
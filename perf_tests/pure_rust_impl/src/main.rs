@@ -2,13 +2,31 @@
 #![allow(dead_code)]
 
 use cadcad_rs::*;
+use std::collections::BTreeMap;
 use rand::Rng;
 
+// Log records of cadcad_rs go to stdout
+struct StdoutLogger;
+
+impl log::Log for StdoutLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool { true }
+    fn log(&self, record: &log::Record) { println!("{}", record.args()); }
+    fn flush(&self) {}
+}
+
+static LOGGER: StdoutLogger = StdoutLogger;
+
 fn main() {
     println!("\n###################### cadCAD.rs ######################\n");
 
+    // e.g. LevelFilter::Info: no per-run output, LevelFilter::Off: no output
+    log::set_logger(&LOGGER).expect("-- A logger was already set");
+    log::set_max_level(log::LevelFilter::Debug);
+
     let cadcad_config = create_config();
-    run_simulation(&cadcad_config);
+    if let Err(err) = run_simulation(&cadcad_config) {
+        println!("--- Simulation failed: {}", err);
+    }
     
     println!("\n######################### END #########################\n\n\n");
 }
@@ -20,7 +38,16 @@ fn create_config() -> cadCADConfig<'static> {
     // Sim config.
     let sim_config = SimConfig { 
         n_run: 1,
-        timesteps: 100_000
+        timesteps: 100_000,
+        params: ParamSweep::from([
+            ("max_preys".to_string(), vec![Value::I32(3000)]),
+        ]),
+        seed: None, // e.g. Some(42) for reproducible runs
+        strict_state_keys: false,
+        record_stride: 1,     // e.g. 100: the states of every 100th timestep
+        record_keys: None,    // e.g. Some(vec!["preys".to_string()])
+        final_state_only: false,
+        steady_state: None,   // e.g. Some(SteadyState { tolerance: 1.0, window: 100, keys: None })
     };
     let print_trajectory = false;
 
@@ -33,54 +60,78 @@ fn create_config() -> cadCADConfig<'static> {
         name: "Using pure Rust".to_string(),
         sim_config,
         init_state,
-        policies: &[
-            prey_change_normal_conditions,
-            prey_pandemic, // enable this to test addable signals
-            predator_change_normal_conditions
+        partial_state_update_blocks: &[
+            PartialStateUpdateBlock {
+                policies: &[
+                    prey_change_normal_conditions,
+                    prey_pandemic, // enable this to test addable signals
+                    predator_change_normal_conditions
+                ],
+                variables: &[
+                    StateKeyAndUpdateFn { key: "preys", update_func: update_prey },
+                    StateKeyAndUpdateFn { key: "predators", update_func: update_predator },
+                ],
+            },
+        ],
+        print_trajectory,
+        execution_mode: ExecutionMode::SingleThreaded,
+        // execution_mode: ExecutionMode::MultiThreaded { n_workers: 0 }, // 0: one worker per CPU
+        sink: None, // e.g. Some(SinkConfig { path: "trajectory.csv".into(), format: SinkFormat::Csv, keep_trajectories: false })
+        signal_aggregation: BTreeMap::from([
+            ("preys_change".to_string(), Aggregation::Sum), // e.g. Aggregation::Min
+        ]),
+        checkpoint: None, // e.g. Some(CheckpointConfig { path: "sim.ckpt".into(), every_timesteps: 10_000 }), see resume_simulation
+        // e.g. stop a run once the preys are extinct
+        stop_conditions: &[
+            // StopCondition { name: "preys extinct", predicate: &preys_extinct },
         ],
-        state_key_and_update_fn_s: &[
-            StateKeyAndUpdateFn { key: "preys", update_func: update_prey },
-            StateKeyAndUpdateFn { key: "predators", update_func: update_predator },
+        // e.g. a pandemic from timestep 500 to 600 only
+        interventions: &[
+            // Intervention { name: "no pandemic", trigger: Trigger::At(1), actions: &[Action::DisablePolicy(prey_pandemic)] },
+            // Intervention { name: "pandemic", trigger: Trigger::At(500), actions: &[Action::EnablePolicy(prey_pandemic)] },
+            // Intervention { name: "end of pandemic", trigger: Trigger::At(601), actions: &[Action::DisablePolicy(prey_pandemic)] },
         ],
-        print_trajectory
     }
 }
 
-// Params
-const MAX_PREYS: i32 = 3000;
+// Stop conditions
+fn preys_extinct(state: &State, _params: &Params) -> bool {
+    matches!(state["preys"], Value::I32(preys) if preys <= 0)
+}
 
 // Policies
-fn prey_change_normal_conditions(state: &State) -> Signal {
+fn prey_change_normal_conditions(state: &State, context: &mut Context) -> PolicySignals {
     let mut preys = 0;
     if let Value::I32(val) =  state["preys"] {
         preys = val
     }
-    let mut random = rand::thread_rng();
+    let mut max_preys = 0;
+    if let Value::I32(val) =  context.params["max_preys"] {
+        max_preys = val
+    }
     // Assuming: preys_change goes down with every iteration since
-    // natural resources limits the number of preys to MAX_PREYS 
-    let preys_change = if preys < MAX_PREYS { random.gen_range(0..MAX_PREYS-preys) } else { 0 };
-    Signal { key: "preys_change".to_string(), value: Value::I32(preys_change) }
+    // natural resources limits the number of preys to max_preys 
+    let preys_change = if preys < max_preys { context.rng.gen_range(0..max_preys-preys) } else { 0 };
+    Signal { key: "preys_change".to_string(), value: Value::I32(preys_change) }.into()
 }
 
-fn prey_pandemic(_state: &State) -> Signal {   
-    let mut random = rand::thread_rng();
-    let preys_change = random.gen_range(-800..-700);
-    Signal { key: "preys_change".to_string(), value: Value::I32(preys_change) }
+fn prey_pandemic(_state: &State, context: &mut Context) -> PolicySignals {   
+    let preys_change = context.rng.gen_range(-800..-700);
+    Signal { key: "preys_change".to_string(), value: Value::I32(preys_change) }.into()
 }
 
-fn predator_change_normal_conditions(_state: &State) -> Signal {
-    let mut random = rand::thread_rng();
-    let predators_change = random.gen_range(-10.0..10.0);
-    Signal { key: "predators_change".to_string(), value: Value::F64(predators_change) }
+fn predator_change_normal_conditions(_state: &State, context: &mut Context) -> PolicySignals {
+    let predators_change = context.rng.gen_range(-10.0..10.0);
+    Signal { key: "predators_change".to_string(), value: Value::F64(predators_change) }.into()
 }
 
 // State update fns
-fn update_prey(state: &State, signals: &Signals) -> Update {
+fn update_prey(state: &State, signals: &Signals, _params: &Params) -> Update {
     let preys_new = state["preys"] + signals["preys_change"];
     Update { key: "preys".to_string(), value: preys_new }
 }
 
-fn update_predator(state: &State, signals: &Signals) -> Update {
+fn update_predator(state: &State, signals: &Signals, _params: &Params) -> Update {
     let predators_new = state["predators"] + signals["predators_change"];
     Update { key: "predators".to_string(), value: predators_new }
 }
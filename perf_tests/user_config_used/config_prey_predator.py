@@ -7,6 +7,9 @@ import cadcad_rs, random
 sim_config = {
     'T': 100_000,  # timesteps
     'N': 1,   # times the simulation will be run (Monte Carlo runs)
+    'M': {    # params
+        'MAX_PREYS': 3000,
+    },
 }
 
 ##
@@ -18,44 +21,44 @@ init_state = {
     'predators':  200.0,
 }
 
-## Params
-MAX_PREYS = 3000
-
 ## Policies
-def prey_change_normal_conditions(state, y):
+def prey_change_normal_conditions(state, params):
     preys =  state['preys']
     # Assuming: preys_change goes down with every iteration since
     # natural resources limits the number of preys to MAX_PREYS 
-    preys_change = random.randint(0, MAX_PREYS-preys) if preys < MAX_PREYS else 0
+    preys_change = random.randint(0, params['MAX_PREYS']-preys) if preys < params['MAX_PREYS'] else 0
     return ( "preys_change", preys_change )
 
-def predator_change_normal_conditions(state, y):
+def predator_change_normal_conditions(state, params):
     return ( "predators_change", random.uniform(-10.0, 10.0) )   
 
-policies = [
-    prey_change_normal_conditions, 
-    predator_change_normal_conditions
-]
-
 # SUFS/Mechanisms
-def update_prey(state, signals):
+def update_prey(state, signals, params):
     preys = state['preys'] + signals['preys_change']
     return ('preys', preys)
 
-def update_predator(state, signals):
+def update_predator(state, signals, params):
     predators = state['predators'] + signals['predators_change']
     return ('predators', predators)
 
-state_update_fns = [
-    update_prey,
-    update_predator
+## Partial state update blocks (each block is a substep)
+partial_state_update_blocks = [
+    {
+        'policies': [
+            prey_change_normal_conditions, 
+            predator_change_normal_conditions
+        ],
+        'variables': [
+            update_prey,
+            update_predator
+        ]
+    },
 ]
 
 cadcad_rs.run_simulation(
   "config from python",
   sim_config,
   init_state,
-  policies,
-  state_update_fns,
+  partial_state_update_blocks,
   print_trajectory
 )
\ No newline at end of file
@@ -0,0 +1,192 @@
+"""cadCAD's configuration and execution API, on top of
+cadcad_rs.run_simulation with cadcad_signatures. A cadCAD model switches
+engine by importing from cadcad_rs.compat instead of cadCAD (e.g. "from
+cadcad_rs.compat.engine import Executor"). Exogenous states and env
+processes are not supported"""
+
+import sys, types, warnings
+from itertools import groupby
+
+# Filled by append_configs, as cadCAD.configs
+configs = []
+
+def config_sim(config_dict):
+    """A sim config, or one per param subset when `M` has list values (of
+    length 1 or equal lengths)"""
+    config_dict = dict(config_dict)
+    params = config_dict.setdefault("M", {})
+    lengths = {len(value) for value in params.values() if isinstance(value, list)} - {0}
+    if not lengths:
+        return config_dict
+    if len(lengths - {1}) > 1:
+        raise ValueError("When sweeping, `M` list lengths should either be 1 and/or equal")
+    return [
+        dict(config_dict, M={
+            key: value[min(s, len(value) - 1)] if isinstance(value, list) and value else value
+            for key, value in params.items()
+        })
+        for s in range(max(lengths))
+    ]
+
+class Configuration:
+    """A param subset of a model, see Experiment.append_model"""
+    def __init__(self, user_id, model_id, subset_id, simulation_id, experiment_id, sim_config,
+                 initial_state, partial_state_update_blocks, policy_ops):
+        self.user_id = user_id
+        self.model_id = model_id
+        self.subset_id = subset_id
+        self.simulation_id = simulation_id
+        self.experiment_id = experiment_id
+        self.run_id = 0
+        self.session_id = f"{user_id}={simulation_id}_{subset_id}"
+        self.sim_config = sim_config
+        self.initial_state = initial_state
+        self.partial_state_update_blocks = partial_state_update_blocks
+        self.policy_ops = policy_ops
+
+    def __repr__(self):
+        return f"Configuration({self.model_id!r}, simulation {self.simulation_id}, subset {self.subset_id})"
+
+class Experiment:
+    def __init__(self, configs=None, experiment_id=0):
+        self.configs = [] if configs is None else configs
+        self.experiment_id = experiment_id
+
+    def append_model(self, sim_configs={}, initial_state={}, seeds={}, raw_exogenous_states={},
+                     env_processes={}, partial_state_update_blocks={}, policy_ops=[],
+                     user_id="cadCAD_user", model_id="sys_model", **kwargs):
+        if raw_exogenous_states or env_processes:
+            raise NotImplementedError("cadcad_rs does not support exogenous states and env processes")
+        if seeds:
+            warnings.warn("cadcad_rs ignores seeds, use the 'seed' sim config key", stacklevel=2)
+        if isinstance(partial_state_update_blocks, dict):
+            partial_state_update_blocks = list(partial_state_update_blocks.values())
+        sim_configs = sim_configs if isinstance(sim_configs, list) else [sim_configs]
+        simulation_id = max((config.simulation_id + 1 for config in self.configs), default=0)
+        for subset_id, sim_config in enumerate(sim_configs):
+            self.configs.append(Configuration(
+                user_id, model_id, subset_id, simulation_id, self.experiment_id, sim_config,
+                initial_state, partial_state_update_blocks, list(policy_ops)
+            ))
+
+    append_configs = append_model
+
+def append_configs(*args, config_list=configs, **kwargs):
+    Experiment(config_list).append_model(*args, **kwargs)
+
+class _PolicyOps:
+    """The policies of a block called as one, their signals combined as with
+    cadCAD's policy_ops: the first op reduces the values of a signal key,
+    the next ones are applied in turn to the result"""
+    def __init__(self, policies, ops):
+        self.policies = policies
+        self.ops = ops
+        self.__name__ = "policy_ops(" + ", ".join(getattr(f, "__name__", repr(f)) for f in policies) + ")"
+
+    def __call__(self, params, substep, state_history, previous_state):
+        signals = {}
+        for policy in self.policies:
+            for key, value in (policy(params, substep, state_history, previous_state) or {}).items():
+                signals[key] = self.ops[0](signals[key], value) if key in signals else value
+        for op in self.ops[1:]:
+            signals = {key: op(value) for key, value in signals.items()}
+        return signals
+
+def _functions(functions):
+    return list(functions.values()) if isinstance(functions, dict) else list(functions)
+
+def _blocks(config):
+    blocks = []
+    for block in config.partial_state_update_blocks:
+        policies = _functions(block.get("policies", {}))
+        if config.policy_ops and policies:
+            policies = [_PolicyOps(policies, config.policy_ops)]
+        # A dict of variables declares the state keys of its functions, as in cadCAD
+        variables = block.get("variables", {})
+        variables = dict(variables) if isinstance(variables, dict) else list(variables)
+        blocks.append({"policies": policies, "variables": variables})
+    return blocks
+
+def _tensor_field(config):
+    def named(functions):
+        if isinstance(functions, dict):
+            return functions
+        return {getattr(f, "__name__", repr(f)): f for f in functions}
+    rows = [
+        dict(named(block.get("policies", {})), **named(block.get("variables", {})), m=m)
+        for m, block in enumerate(config.partial_state_update_blocks, 1)
+    ]
+    try:
+        import pandas
+    except ImportError:
+        return rows
+    return pandas.DataFrame(rows)
+
+class ExecutionMode:
+    local_mode = single_mode = single_proc = "single_proc"
+    multi_mode = multi_proc = parallelized = "multi_proc"
+
+class ExecutionContext:
+    def __init__(self, context=ExecutionMode.local_mode, method=None, additional_objs=None):
+        if context not in ("single_proc", "multi_proc"):
+            raise ValueError(f"Unknown execution mode {context!r}, see ExecutionMode")
+        self.name = context
+        self.method = method
+        self.additional_objs = additional_objs
+
+class Executor:
+    def __init__(self, exec_context, configs, sc=None, empty_return=False, supress_print=False):
+        self.exec_context = exec_context
+        self.configs = configs
+        self.empty_return = empty_return
+
+    def execute(self):
+        """(raw_result, tensor_field, sessions): the states of every run as
+        flat records, the tensor field of the model (of every config if
+        several) and the sessions of the configs"""
+        from cadcad_rs import run_simulation
+        if self.empty_return:
+            return [], None, []
+        raw_result = []
+        # The param subsets of a model are run as one sweep
+        for simulation_id, group in groupby(self.configs, key=lambda config: config.simulation_id):
+            group = list(group)
+            config = group[0]
+            sim_config = {key: value for key, value in config.sim_config.items() if key != "M"}
+            T = sim_config["T"]
+            sim_config["T"] = len(T) if isinstance(T, range) else T
+            # Wrapped in lists, so list values are not swept again
+            sim_config["M"] = {
+                key: [c.sim_config.get("M", {})[key] for c in group] for key in config.sim_config.get("M", {})
+            }
+            sim_config["cadcad_signatures"] = True
+            result = run_simulation(
+                config.model_id, sim_config, dict(config.initial_state), _blocks(config), False,
+                execution_mode=self.exec_context.name
+            )
+            for trajectory in result:
+                for state in trajectory:
+                    state["simulation"] = simulation_id
+                    raw_result.append(state)
+        tensor_fields = [_tensor_field(config) for config in self.configs]
+        sessions = [
+            {key: getattr(config, key) for key in
+             ("user_id", "experiment_id", "session_id", "simulation_id", "run_id", "subset_id")}
+            for config in self.configs
+        ]
+        return raw_result, tensor_fields[0] if len(tensor_fields) == 1 else tensor_fields, sessions
+
+def _submodule(name, **attributes):
+    module = types.ModuleType(f"{__name__}.{name}")
+    module.__dict__.update(attributes)
+    sys.modules[module.__name__] = module
+    return module
+
+# cadCAD's modules
+configuration = _submodule(
+    "configuration", Configuration=Configuration, Experiment=Experiment, append_configs=append_configs
+)
+configuration.utils = _submodule("configuration.utils", config_sim=config_sim)
+engine = _submodule(
+    "engine", ExecutionMode=ExecutionMode, ExecutionContext=ExecutionContext, Executor=Executor
+)
@@ -12,11 +12,125 @@ pub type Trajectory<'a> = Vec<&'a State>;
 pub type Signals = PyDict;
 pub type UpdateFunc<'a> = &'a PyAny;
 pub type PolicyFunc<'a> = &'a PyAny;
+pub type Params = PyDict;
 
 #[derive(Debug)]
-pub struct SimConfig { 
+pub struct SimConfig<'a> { 
     pub n_run: usize,
-    pub timesteps: usize
+    pub timesteps: usize,
+    pub params: &'a PyDict, // cadCAD's "M", list values are swept
+    pub seed: Option<u64>,  // Master seed, None: Python's randomness not seeded
+    pub policy_rng: bool,   // Pass a per-run `random.Random` to policies
+    // Error on state keys without a state update fn in a substep, instead
+    // of carrying them forward unchanged
+    pub strict_state_keys: bool,
+    // Recording, in the trajectories and sinks: the states of every
+    // record_stride-th timestep (1: all), with only the record_keys state
+    // keys (None: all, the cadCAD keys are always there), or only the final
+    // state. See Recorder
+    pub record_stride: usize,
+    pub record_keys: Option<Vec<String>>,
+    pub final_state_only: bool,
+    // Runs stop early on a stop condition, or once at steady state
+    pub stop_conditions: Vec<StopCondition<'a>>,
+    pub steady_state: Option<SteadyState>,
+    // Call policies as (params, substep, state_history, previous_state) and
+    // state update fns as (params, substep, state_history, previous_state,
+    // policy_input), as cadCAD does. history_window: timesteps of history
+    // kept before the current one (None: all), see StateHistory
+    pub cadcad_signatures: bool,
+    pub history_window: Option<usize>,
+    // Debug mode: the Python functions are checked not to mutate the values
+    // of the state (e.g. append to a list in it), which the read-only state
+    // views they get cannot prevent. Slow, see StateGuard
+    pub debug: bool,
+}
+
+// A (state, params) -> bool callable, called on the state at the end of
+// every timestep: a run stops at the first one it returns true on. name is
+// reported as the reason of the stop
+#[derive(Debug)]
+pub struct StopCondition<'a> {
+    pub name: String,
+    pub func: &'a PyAny,
+}
+
+impl<'a> StopCondition<'a> {
+    // A list of callables, named after their functions, or a dict of them
+    // by name
+    fn from_py(stop_conditions: &'a PyAny) -> PyResult<Vec<Self>> {
+        let conditions = match stop_conditions.downcast::<PyDict>() {
+            Ok(conditions) => conditions.iter()
+                .map(|(name, func)| Ok(StopCondition { name: name.extract()?, func }))
+                .collect::<PyResult<Vec<_>>>()?,
+            Err(_) => stop_conditions.iter()?
+                .map(|func| func.map(|func| StopCondition { name: function_name(func), func }))
+                .collect::<PyResult<Vec<_>>>()?,
+        };
+        match conditions.iter().find(|condition| !condition.func.is_callable()) {
+            Some(condition) => Err(PyValueError::new_err(format!(
+                "Stop condition '{}' should be a (state, params) -> bool callable, got {}",
+                condition.name, repr(condition.func)
+            ))),
+            None => Ok(conditions),
+        }
+    }
+}
+
+// A run is at steady state when none of its state values (of keys, None:
+// all but the cadCAD keys) changed from a timestep to the next, window
+// timesteps in a row. Numbers may change by up to tolerance
+#[derive(Debug, Clone)]
+pub struct SteadyState {
+    pub tolerance: f64,
+    pub window: usize,
+    pub keys: Option<Vec<String>>,
+}
+
+impl SteadyState {
+    // A {"tolerance": 0 (default), "window": 1 (default), "keys": None
+    // (default)} dict
+    fn from_py(steady_state: &PyAny) -> PyResult<Self> {
+        let steady_state = steady_state.downcast::<PyDict>()?;
+        Ok(SteadyState {
+            tolerance: match steady_state.get_item("tolerance") {
+                Some(tolerance) => tolerance.extract::<f64>()?,
+                None => 0.0,
+            },
+            window: match steady_state.get_item("window") {
+                Some(window) => window.extract::<usize>()?.max(1),
+                None => 1,
+            },
+            keys: match steady_state.get_item("keys") {
+                Some(keys) => keys.extract::<Option<Vec<String>>>()?,
+                None => None,
+            },
+        })
+    }
+
+    fn is_unchanged(&self, previous: &State, current: &State) -> PyResult<bool> {
+        let eq = PyModule::import(current.py(), "operator")?.getattr("eq")?;
+        let is_unchanged = |key: &PyAny| match (previous.get_item(key), current.get_item(key)) {
+            (Some(a), Some(b)) => match (a.extract::<f64>(), b.extract::<f64>()) {
+                (Ok(a), Ok(b)) => Ok((a - b).abs() <= self.tolerance),
+                _ => eq.call1((a, b))?.is_true(),
+            },
+            _ => Ok(false),
+        };
+        match &self.keys {
+            Some(keys) => {
+                for key in keys {
+                    if !is_unchanged(PyString::new(current.py(), key))? { return Ok(false); }
+                }
+            },
+            None => {
+                for key in current.keys() {
+                    if !is_cadcad_key(key) && !is_unchanged(key)? { return Ok(false); }
+                }
+            },
+        }
+        Ok(true)
+    }
 }
 
 // Create by state update fns
@@ -33,55 +147,1248 @@ pub struct Signal<'a> {
     pub value: &'a PyAny
 }
 
+// A substep: policies applied on the previous substep's state, then
+// state update fns applied with the aggregated signals
+pub struct PartialStateUpdateBlock<'a> {
+    pub policies: &'a PyList,
+    pub variables: &'a PyList,
+    // The state keys of the variables, when given as a dict as in cadCAD
+    pub keys: Option<Vec<String>>,
+}
+
+// What to do with a run whose policy/SUF fails
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorPolicy {
+    Abort,       // Raise, the other runs are not returned
+    SkipRun,     // Drop the failed run's trajectory, continue with the others
+    TruncateRun, // Keep the failed run's trajectory up to the failure
+}
+
+impl ErrorPolicy {
+    fn from_name(name: &str) -> PyResult<Self> {
+        match name {
+            "abort" => Ok(Self::Abort),
+            "skip_run" => Ok(Self::SkipRun),
+            "truncate_run" => Ok(Self::TruncateRun),
+            other => Err(PyValueError::new_err(format!(
+                "Unknown error policy '{}', expected 'abort', 'skip_run' or 'truncate_run'", other
+            ))),
+        }
+    }
+}
+
+// How the signals of several policies with the same key are combined
+pub enum Aggregation<'a> {
+    LastWriterWins,
+    // A (previous, new) -> aggregated fn, e.g. operator.add, or a custom
+    // callable
+    Reduce { name: String, func: &'a PyAny },
+}
+
+impl<'a> Aggregation<'a> {
+    // From a reducer name or a custom callable
+    fn from_py(py: Python<'a>, aggregation: &'a PyAny) -> PyResult<Self> {
+        if aggregation.is_callable() {
+            return Ok(Self::Reduce { name: function_name(aggregation), func: aggregation });
+        }
+        let name = aggregation.extract::<&str>()?;
+        let (module, func) = match name {
+            "add" => ("operator", "add"),
+            "product" => ("operator", "mul"),
+            "min" => ("builtins", "min"),
+            "max" => ("builtins", "max"),
+            "concat" => ("operator", "concat"),
+            "last" => return Ok(Self::LastWriterWins),
+            other => return Err(PyValueError::new_err(format!(
+                "Unknown signal aggregation '{}', expected 'add', 'product', 'min', 'max', \
+                 'concat', 'last' or a callable", other
+            ))),
+        };
+        let func = PyModule::import(py, module)?.getattr(func)?;
+        Ok(Self::Reduce { name: name.to_string(), func })
+    }
+}
+
 #[allow(non_camel_case_types)]
 pub struct cadCADConfig<'a> {
     pub name: String,
-    pub sim_config: SimConfig,
+    pub sim_config: SimConfig<'a>,
     pub init_state: &'a State,
-    pub policies: &'a PyList,
-    pub state_update_functions: &'a PyList,
+    pub partial_state_update_blocks: Vec<PartialStateUpdateBlock<'a>>,
     pub print_trajectory: bool,
+    pub error_policy: ErrorPolicy,
+    // Per signal key, keys not listed are added (see Aggregation)
+    pub signal_aggregation: std::collections::HashMap<String, Aggregation<'a>>,
+    pub default_aggregation: Aggregation<'a>,
+    // File the states are written to as they are produced, see StateSink
+    pub sink: Option<String>,
+    // Keep the trajectories in memory (in the result data), else only the
+    // current state of a run is kept
+    pub keep_trajectories: bool,
+    pub checkpoint: Option<CheckpointConfig>,
+    pub interventions: Vec<Intervention<'a>>,
+}
+
+// Writes the states of runs to a file, in a format given by its extension:
+// .csv, .jsonl or .parquet (with pyarrow, a row group per run, so a run's
+// states are kept until its end)
+pub enum StateSink<'py> {
+    Csv { file: &'py PyAny, writer: Option<&'py PyAny> },
+    Jsonl { file: &'py PyAny, dumps: &'py PyAny, kwargs: &'py PyDict },
+    Parquet { path: String, rows: &'py PyList, writer: Option<&'py PyAny> },
+}
+
+impl<'py> StateSink<'py> {
+    pub fn create(py: Python<'py>, path: &str) -> PyResult<Self> {
+        let open = |newline: Option<&str>| {
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("newline", newline)?;
+            PyModule::import(py, "builtins")?.getattr("open")?.call((path, "w"), Some(kwargs))
+        };
+        let extension = std::path::Path::new(path).extension().and_then(|ext| ext.to_str());
+        match extension {
+            Some("csv") => Ok(Self::Csv { file: open(Some(""))?, writer: None }),
+            Some("jsonl") => {
+                let kwargs = PyDict::new(py);
+                kwargs.set_item("default", PyModule::import(py, "builtins")?.getattr("repr")?)?;
+                Ok(Self::Jsonl {
+                    file: open(None)?,
+                    dumps: PyModule::import(py, "json")?.getattr("dumps")?,
+                    kwargs,
+                })
+            },
+            Some("parquet") => {
+                PyModule::import(py, "pyarrow.parquet")?;
+                Ok(Self::Parquet { path: path.to_string(), rows: PyList::empty(py), writer: None })
+            },
+            _ => Err(PyValueError::new_err(format!(
+                "Unknown sink format of '{}', expected a .csv, .jsonl or .parquet file", path
+            ))),
+        }
+    }
+
+    pub fn write_state(&mut self, state: &'py State) -> PyResult<()> {
+        let py = state.py();
+        match self {
+            // Columns of the first state: keys added later (e.g. by an
+            // intervention) are left out, missing ones are empty
+            Self::Csv { file, writer } => {
+                if writer.is_none() {
+                    let kwargs = PyDict::new(py);
+                    kwargs.set_item("fieldnames", state.keys())?;
+                    kwargs.set_item("extrasaction", "ignore")?;
+                    kwargs.set_item("restval", "")?;
+                    let dict_writer = PyModule::import(py, "csv")?.getattr("DictWriter")?
+                        .call((*file,), Some(kwargs))?;
+                    dict_writer.call_method0("writeheader")?;
+                    *writer = Some(dict_writer);
+                }
+                writer.unwrap().call_method1("writerow", (state,))?;
+            },
+            Self::Jsonl { file, dumps, kwargs } => {
+                let line = dumps.call((state,), Some(*kwargs))?.extract::<String>()?;
+                file.call_method1("write", (line + "\n",))?;
+            },
+            Self::Parquet { rows, .. } => rows.append(state)?,
+        }
+        Ok(())
+    }
+
+    // Parquet sinks keep the states of a run until its end
+    pub fn keeps_states(&self) -> bool {
+        matches!(self, Self::Parquet { .. })
+    }
+
+    pub fn end_run(&mut self) -> PyResult<()> {
+        if let Self::Parquet { path, rows, writer } = self {
+            if rows.is_empty() { return Ok(()); }
+            let py = rows.py();
+            let table = PyModule::import(py, "pyarrow")?.getattr("Table")?
+                .call_method1("from_pylist", (*rows,))?;
+            if writer.is_none() {
+                *writer = Some(PyModule::import(py, "pyarrow.parquet")?.getattr("ParquetWriter")?
+                    .call1((path.as_str(), table.getattr("schema")?))?);
+            }
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("row_group_size", rows.len())?;
+            writer.unwrap().call_method("write_table", (table,), Some(kwargs))?;
+            rows.call_method0("clear")?;
+        }
+        Ok(())
+    }
+
+    pub fn close(mut self) -> PyResult<()> {
+        self.end_run()?;
+        match self {
+            Self::Csv { file, .. } | Self::Jsonl { file, .. } => file.call_method0("close")?,
+            Self::Parquet { writer: Some(writer), .. } => writer.call_method0("close")?,
+            Self::Parquet { writer: None, .. } => return Ok(()),
+        };
+        Ok(())
+    }
+}
+
+// Sink/checkpoint file of worker w in "multi_proc" mode, e.g. out.csv -> out_0.csv
+fn worker_path(path: &str, w: usize) -> String {
+    let path = std::path::Path::new(path);
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("");
+    let file_name = match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => format!("{}_{}.{}", stem, w, ext),
+        None => format!("{}_{}", stem, w),
+    };
+    path.with_file_name(file_name).to_string_lossy().into_owned()
+}
+
+// dict recording the keys read from it, and the missing ones, see validate
+const TRACING_DICT: &str = include_str!("tracing_dict.py");
+
+// Read-only view of the states of a run so far, passed to policies and
+// state update fns with cadcad_signatures. Only the last window+1
+// timesteps are referenced with a history_window. Its states are never
+// reused for the next ones (see run_single_simulation): views of them may
+// be kept by the Python functions, even once out of the window
+const STATE_HISTORY: &str = include_str!("state_history.py");
+
+fn new_state_history<'py>(
+    py: Python<'py>, sim_config: &SimConfig, timesteps: &PyAny, length: usize
+) -> PyResult<&'py PyAny> {
+    PyModule::import(py, "cadcad_rs")?.getattr("StateHistory")?
+        .call1((timesteps, length, sim_config.history_window))
+}
+
+// Trajectories of the runs, a dict per failed run (see failed_run) and a
+// dict per run (see RunReport). interrupted is the error (e.g.
+// KeyboardInterrupt) the simulation was stopped by, the runs are then
+// those done so far
+pub struct ResultData {
+    pub trajectories: Vec<Vec<PyObject>>,
+    pub failed_runs: Vec<PyObject>,
+    pub run_reports: Vec<PyObject>,
+    pub interrupted: Option<PyErr>,
+}
+
+// What a run took: steps are the substeps run, states_recorded the states
+// written to the trajectory and/or sink, memory_bytes an estimate of the
+// trajectory kept in the results (see state_memory). stop is the timestep
+// a run stopped at and why, None if it went up to its last timestep.
+// interventions are the (timestep, name) of those which fired, in order
+#[derive(Debug, Clone)]
+pub struct RunReport {
+    pub subset: usize,
+    pub run: usize,
+    pub elapsed: std::time::Duration,
+    pub steps: usize,
+    pub states_recorded: usize,
+    pub memory_bytes: usize,
+    pub stop: Option<(usize, String)>,
+    pub interventions: Vec<(usize, String)>,
+}
+
+impl RunReport {
+    fn to_object(&self, py: Python) -> PyResult<PyObject> {
+        let report = PyDict::new(py);
+        report.set_item("subset", self.subset)?;
+        report.set_item("run", self.run)?;
+        report.set_item("elapsed", self.elapsed.as_secs_f64())?;
+        report.set_item("steps", self.steps)?;
+        report.set_item("states_recorded", self.states_recorded)?;
+        report.set_item("memory_bytes", self.memory_bytes)?;
+        report.set_item("stopped_at", self.stop.as_ref().map(|(timestep, _)| timestep))?;
+        report.set_item("stop_reason", self.stop.as_ref().map(|(_, reason)| reason))?;
+        report.set_item("interventions", &self.interventions)?;
+        Ok(report.into())
+    }
+}
+
+// ---- Logging ----
+
+// The levels of Python's logging module
+#[derive(Debug, Clone, Copy)]
+pub enum LogLevel {
+    Debug = 10,   // per run: config, params, seed, report
+    Info = 20,    // per simulation
+    Warning = 30, // failed runs
+}
+
+// Human-readable output goes to the "cadcad_rs" logger of Python's logging
+// module, so it is configured (or silenced) like any other logger. Messages
+// are only formatted when logged, and logging never fails a simulation
+fn log(py: Python, level: LogLevel, message: impl FnOnce() -> String) {
+    let logger = PyModule::import(py, "logging")
+        .and_then(|logging| logging.call_method1("getLogger", ("cadcad_rs",)));
+    if let Ok(logger) = logger {
+        let level = level as u8;
+        if logger.call_method1("isEnabledFor", (level,)).map_or(false, |enabled| enabled.is_true().unwrap_or(false)) {
+            let _todo = logger.call_method1("log", (level, message()));
+        }
+    }
+}
+
+// ---- Progress ----
+
+enum ProgressReporter {
+    Callback(PyObject),
+    Bar(PyObject), // tqdm.tqdm
+}
+
+// Progress of the runs of a simulation, reported every interval timesteps
+// of a run and after each run: progress(runs_done, n_runs, timestep,
+// timesteps) is called, or a tqdm progress bar of n_runs * timesteps
+// timesteps is updated
+pub struct Progress {
+    reporter: ProgressReporter,
+    interval: usize,
+    n_runs: usize,
+    timesteps: usize,
+    runs_done: usize,
+    position: usize, // of the bar
+}
+
+impl Progress {
+    fn check(progress: &PyAny) -> PyResult<()> {
+        match progress.is_none() || progress.is_callable() || progress.downcast::<PyBool>().is_ok() {
+            true => Ok(()),
+            false => Err(PyValueError::new_err(format!(
+                "Progress should be a (runs_done, n_runs, timestep, timesteps) callable or a bool, got {}",
+                repr(progress)
+            ))),
+        }
+    }
+
+    // progress: None, a callable or a bool (True: tqdm progress bar). The
+    // default interval is 1% of the timesteps
+    fn from_py(
+        py: Python, progress: &PyAny, interval: Option<usize>, n_runs: usize, timesteps: usize
+    ) -> PyResult<Option<Self>> {
+        Self::check(progress)?;
+        let reporter = if progress.is_none() {
+            return Ok(None);
+        }
+        else if let Ok(progress) = progress.downcast::<PyBool>() {
+            if !progress.is_true() { return Ok(None); }
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("total", n_runs * timesteps)?;
+            kwargs.set_item("unit", "timestep")?;
+            let bar = PyModule::import(py, "tqdm")?.getattr("tqdm")?.call((), Some(kwargs))?;
+            ProgressReporter::Bar(bar.into())
+        }
+        else {
+            ProgressReporter::Callback(progress.into())
+        };
+        let interval = interval.unwrap_or(timesteps / 100).max(1);
+        Ok(Some(Progress { reporter, interval, n_runs, timesteps, runs_done: 0, position: 0 }))
+    }
+
+    // timestep is 1-based, the last one is reported by runs_done
+    fn timestep_done(&mut self, py: Python, timestep: usize) -> PyResult<()> {
+        match timestep % self.interval == 0 && timestep < self.timesteps {
+            true => self.report(py, timestep),
+            false => Ok(()),
+        }
+    }
+
+    fn runs_done(&mut self, py: Python, n_runs: usize) -> PyResult<()> {
+        self.runs_done += n_runs;
+        self.report(py, 0)
+    }
+
+    fn report(&mut self, py: Python, timestep: usize) -> PyResult<()> {
+        match &self.reporter {
+            ProgressReporter::Callback(callback) => {
+                callback.call1(py, (self.runs_done, self.n_runs, timestep, self.timesteps))?;
+            },
+            ProgressReporter::Bar(bar) => {
+                let position = self.runs_done * self.timesteps + timestep;
+                bar.call_method1(py, "update", (position - self.position,))?;
+                self.position = position;
+            },
+        }
+        Ok(())
+    }
+
+    fn close(self, py: Python) -> PyResult<()> {
+        if let ProgressReporter::Bar(bar) = self.reporter { bar.call_method0(py, "close")?; }
+        Ok(())
+    }
 }
 
+// ---- Checkpoints ----
+
+// A checkpoint is files of pickles, each after its length (u64,
+// little-endian), starting with the same header: (CHECKPOINT_FORMAT,
+// CHECKPOINT_VERSION, (name, N, T, number of param subsets, seed), the
+// (param subset, run) pairs of the process). The file at the path then has
+// ((s, i), {"trajectory", "failed_run", "report"}), appended as run i of
+// param subset s finishes. Each run in progress has its own file, at the
+// path + ".run<s>_<i>", with its checkpoints {"timestep", "state",
+// "trajectory", "states_recorded", "steady_timesteps", "params",
+// "disabled_policies", "triggered", "interventions", "history",
+// "random_states"} (see RunInterventions and StateHistory), whose
+// "trajectory" and "history" have the states since the previous one. It is
+// removed once the run is done. So nothing is written twice. States and
+// params have to be picklable
+const CHECKPOINT_FORMAT: &str = "cadcad_rs checkpoint";
+const CHECKPOINT_VERSION: u32 = 3;
+
+#[derive(Debug, Clone)]
+pub struct CheckpointConfig {
+    pub path: String,
+    // Timesteps between two checkpoints of a run, runs are also
+    // checkpointed when done
+    pub interval: usize,
+    // Go on from the checkpoint at path, else it is overwritten
+    pub resume: bool,
+}
+
+pub struct Checkpointer<'py> {
+    config: CheckpointConfig,
+    header: &'py PyAny,
+    done: &'py PyDict, // Of the checkpoint resumed
+    done_file: std::fs::File,
+}
+
+// The file of a run in progress, which its checkpoints are appended to
+pub struct RunFile {
+    path: String,
+    file: Option<std::fs::File>, // Opened by its first checkpoint, or when resumed
+    states_saved: usize, // Of the trajectory
+    timesteps_saved: usize, // Of the state history
+}
+
+impl<'py> Checkpointer<'py> {
+    fn create(
+        py: Python<'py>, cadcad_config: &cadCADConfig, runs: &[(usize, usize)], n_subset: usize
+    ) -> PyResult<Option<Self>> {
+        let config = match &cadcad_config.checkpoint {
+            Some(config) => config.clone(),
+            None => return Ok(None),
+        };
+        let sim_config = &cadcad_config.sim_config;
+        let simulation = (
+            cadcad_config.name.clone(), sim_config.n_run, sim_config.timesteps, n_subset, sim_config.seed
+        );
+        let header = (CHECKPOINT_FORMAT, CHECKPOINT_VERSION, simulation.clone(), runs.to_vec()).to_object(py);
+        let header = header.into_ref(py);
+        let path = &config.path;
+        if !config.resume {
+            let done_file = std::fs::File::create(path).map_err(|err|
+                PyOSError::new_err(format!("Cannot write checkpoint {}: {}", path, err))
+            )?;
+            append_pickle(py, &done_file, path, header)?;
+            // Runs in progress of the previous checkpoint
+            for &(s, i) in runs { remove_run_file(path, s, i)?; }
+            return Ok(Some(Checkpointer { config, header, done: PyDict::new(py), done_file }));
+        }
+
+        let bytes = std::fs::read(path).map_err(|err|
+            PyOSError::new_err(format!("Cannot read checkpoint {}: {}", path, err))
+        )?;
+        let (pickles, len_read) = read_pickles(py, &bytes)?;
+        let not_a_checkpoint = || PyValueError::new_err(format!("{} is not a cadcad_rs checkpoint", path));
+        let checkpointed = pickles.first()
+            .and_then(|checkpointed| checkpointed.extract::<(&str, u32, &PyAny, &PyAny)>().ok())
+            .filter(|checkpointed| checkpointed.0 == CHECKPOINT_FORMAT)
+            .ok_or_else(not_a_checkpoint)?;
+        let (_, version, checkpointed_simulation, checkpointed_runs) = checkpointed;
+        if version != CHECKPOINT_VERSION {
+            return Err(PyValueError::new_err(format!(
+                "Checkpoint {} has format version {}, expected {}", path, version, CHECKPOINT_VERSION
+            )));
+        }
+        if checkpointed_simulation.extract::<(String, usize, usize, usize, Option<u64>)>()? != simulation {
+            return Err(PyValueError::new_err(format!(
+                "Checkpoint {} is of another simulation: (name, N, T, param subsets, seed) {}, not {}",
+                path, repr(checkpointed_simulation), repr(simulation.to_object(py).as_ref(py))
+            )));
+        }
+        let checkpointed_runs = checkpointed_runs.extract::<Vec<(usize, usize)>>()?;
+        if checkpointed_runs != runs {
+            return Err(PyValueError::new_err(format!(
+                "Checkpoint {} is of (param subset, run) pairs {:?}, not {:?} (another number of workers?)",
+                path, checkpointed_runs, runs
+            )));
+        }
+        let done = PyDict::new(py);
+        for pickle in &pickles[1..] {
+            let (run, outputs) = pickle.extract::<((usize, usize), &PyDict)>()?;
+            done.set_item(run, outputs)?;
+            // Interrupted between the end of the run and the removal of its file
+            remove_run_file(path, run.0, run.1)?;
+        }
+        let done_file = open_append(path, len_read as u64).map_err(|err|
+            PyOSError::new_err(format!("Cannot write checkpoint {}: {}", path, err))
+        )?;
+        Ok(Some(Checkpointer { config, header, done, done_file }))
+    }
+
+    // The outputs of run i of param subset s, if done before the checkpoint
+    fn done_run(&self, s: usize, i: usize) -> PyResult<Option<&'py PyDict>> {
+        match self.done.get_item((s, i)) {
+            Some(run) => Ok(Some(run.downcast::<PyDict>()?)),
+            None => Ok(None),
+        }
+    }
+
+    // The file of run i of param subset s, and its last checkpoint when
+    // resumed, with the states of all its checkpoints. The file is cut
+    // after it, for the next ones to be appended
+    fn run_file(&self, py: Python<'py>, s: usize, i: usize) -> PyResult<(RunFile, Option<&'py PyDict>)> {
+        let path = format!("{}.run{}_{}", self.config.path, s, i);
+        let mut run_file = RunFile { path, file: None, states_saved: 0, timesteps_saved: 0 };
+        if !self.config.resume { return Ok((run_file, None)); }
+        let bytes = match std::fs::read(&run_file.path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok((run_file, None)),
+            Err(err) => return Err(PyOSError::new_err(format!("Cannot read checkpoint {}: {}", run_file.path, err))),
+        };
+        let (pickles, len_read) = read_pickles(py, &bytes)?;
+        // Interrupted while its header was written
+        let (checkpointed, checkpoints) = match pickles.split_first() {
+            Some(pickles) => pickles,
+            None => return Ok((run_file, None)),
+        };
+        if checkpointed.compare(self.header)? != std::cmp::Ordering::Equal {
+            return Err(PyValueError::new_err(format!(
+                "Checkpoint {} has the run of another checkpoint", run_file.path
+            )));
+        }
+        let run = match checkpoints.last() {
+            Some(run) => run.downcast::<PyDict>()?,
+            None => return Ok((run_file, None)),
+        };
+        let trajectory = PyList::empty(py);
+        let history = PyList::empty(py);
+        for checkpoint in checkpoints {
+            let checkpoint = checkpoint.downcast::<PyDict>()?;
+            trajectory.call_method1("extend", (checkpoint_item(checkpoint, "trajectory")?,))?;
+            if let Some(checkpoint_history) = checkpoint.get_item("history") {
+                history.call_method1("extend", (checkpoint_history.get_item(0)?,))?;
+            }
+        }
+        run.set_item("trajectory", trajectory)?;
+        if let Some(run_history) = run.get_item("history") {
+            run_file.timesteps_saved = run_history.get_item(1)?.extract()?;
+            run.set_item("history", (history, run_file.timesteps_saved))?;
+        }
+        run_file.states_saved = trajectory.len();
+        run_file.file = Some(open_append(&run_file.path, len_read as u64).map_err(|err|
+            PyOSError::new_err(format!("Cannot write checkpoint {}: {}", run_file.path, err))
+        )?);
+        Ok((run_file, Some(run)))
+    }
+
+    fn is_due(&self, timestep: usize, timesteps: usize) -> bool {
+        timestep % self.config.interval == 0 && timestep < timesteps
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn save_run(
+        &self, py: Python, run_file: &mut RunFile, timestep: usize, state: &State, recorder: &Recorder,
+        steady_timesteps: usize, interventions: &RunInterventions, history: Option<&PyAny>,
+        rng: Option<&PyAny>
+    ) -> PyResult<()> {
+        let run = PyDict::new(py);
+        run.set_item("timestep", timestep)?;
+        run.set_item("state", state)?;
+        run.set_item("trajectory", PyList::new(py, &recorder.trajectory[run_file.states_saved..]))?;
+        run.set_item("states_recorded", recorder.states_recorded)?;
+        run.set_item("steady_timesteps", steady_timesteps)?;
+        run.set_item("params", interventions.params)?;
+        run.set_item("disabled_policies", interventions.disabled_policies.iter().collect::<Vec<_>>())?;
+        run.set_item("triggered", interventions.triggered.iter().collect::<Vec<_>>())?;
+        run.set_item("interventions", &recorder.interventions)?;
+        let mut timesteps_saved = run_file.timesteps_saved;
+        if let Some(history) = history {
+            let history = history.call_method1("_checkpoint", (timesteps_saved,))?;
+            timesteps_saved = history.get_item(1)?.extract()?;
+            run.set_item("history", history)?;
+        }
+        run.set_item("random_states", random_states(py, rng)?)?;
+        if run_file.file.is_none() {
+            let file = std::fs::File::create(&run_file.path).map_err(|err|
+                PyOSError::new_err(format!("Cannot write checkpoint {}: {}", run_file.path, err))
+            )?;
+            append_pickle(py, &file, &run_file.path, self.header)?;
+            run_file.file = Some(file);
+        }
+        append_pickle(py, run_file.file.as_ref().unwrap(), &run_file.path, run)?;
+        run_file.states_saved = recorder.trajectory.len();
+        run_file.timesteps_saved = timesteps_saved;
+        Ok(())
+    }
+
+    fn finish_run(
+        &self, py: Python, s: usize, i: usize,
+        trajectory: Option<PyObject>, failed_run: Option<PyObject>, report: PyObject
+    ) -> PyResult<()> {
+        let run = PyDict::new(py);
+        run.set_item("trajectory", trajectory)?;
+        run.set_item("failed_run", failed_run)?;
+        run.set_item("report", report)?;
+        append_pickle(py, &self.done_file, &self.config.path, ((s, i), run))?;
+        remove_run_file(&self.config.path, s, i)
+    }
+}
+
+// The pickles of a checkpoint file, and the length of those read: one cut
+// short while being appended is dropped
+fn read_pickles<'py>(py: Python<'py>, bytes: &[u8]) -> PyResult<(Vec<&'py PyAny>, usize)> {
+    let loads = PyModule::import(py, "pickle")?.getattr("loads")?;
+    let mut pickles = Vec::new();
+    let mut len_read = 0;
+    while bytes.len() - len_read >= 8 {
+        let mut len = [0; 8];
+        len.copy_from_slice(&bytes[len_read..len_read+8]);
+        let len = u64::from_le_bytes(len) as usize;
+        match bytes[len_read+8..].get(..len) {
+            Some(pickle) => pickles.push(loads.call1((PyBytes::new(py, pickle),))?),
+            None => break,
+        };
+        len_read += 8 + len;
+    }
+    Ok((pickles, len_read))
+}
+
+fn append_pickle(py: Python, file: &std::fs::File, path: &str, object: impl ToPyObject) -> PyResult<()> {
+    let bytes = PyModule::import(py, "pickle")?.call_method1("dumps", (object.to_object(py),))?;
+    let bytes = bytes.downcast::<PyBytes>()?.as_bytes();
+    let append = |mut file: &std::fs::File| -> std::io::Result<()> {
+        let mut pickle = (bytes.len() as u64).to_le_bytes().to_vec();
+        pickle.extend_from_slice(bytes);
+        std::io::Write::write_all(&mut file, &pickle)?;
+        file.sync_data()
+    };
+    append(file).map_err(|err| PyOSError::new_err(format!("Cannot write checkpoint {}: {}", path, err)))
+}
+
+// The file at path, cut to its first len bytes, opened to append to them
+fn open_append(path: &str, len: u64) -> std::io::Result<std::fs::File> {
+    let file = std::fs::OpenOptions::new().append(true).open(path)?;
+    file.set_len(len)?;
+    Ok(file)
+}
+
+fn remove_run_file(path: &str, s: usize, i: usize) -> PyResult<()> {
+    let path = format!("{}.run{}_{}", path, s, i);
+    match std::fs::remove_file(&path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(PyOSError::new_err(format!(
+            "Cannot remove checkpoint {}: {}", path, err
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn checkpoint_item<'a>(checkpoint: &'a PyDict, key: &str) -> PyResult<&'a PyAny> {
+    checkpoint.get_item(key).ok_or_else(||
+        PyKeyError::new_err(format!("Checkpoint has no '{}' key", key))
+    )
+}
+
+// States of random, numpy.random (None if not importable) and the run's own
+// random.Random (None without policy_rng), see seed_py_random
+fn random_states(py: Python, rng: Option<&PyAny>) -> PyResult<PyObject> {
+    let random_state = PyModule::import(py, "random")?.call_method0("getstate")?;
+    let np_random_state = match PyModule::import(py, "numpy.random") {
+        Ok(np_random) => Some(np_random.call_method0("get_state")?),
+        Err(_) => None,
+    };
+    let rng_state = match rng {
+        Some(rng) => Some(rng.call_method0("getstate")?),
+        None => None,
+    };
+    Ok((random_state, np_random_state, rng_state).to_object(py))
+}
+
+fn set_random_states(py: Python, states: &PyAny, rng: Option<&PyAny>) -> PyResult<()> {
+    let (random_state, np_random_state, rng_state) = states.extract::<(&PyAny, Option<&PyAny>, Option<&PyAny>)>()?;
+    PyModule::import(py, "random")?.call_method1("setstate", (random_state,))?;
+    if let Some(np_random_state) = np_random_state {
+        PyModule::import(py, "numpy.random")?.call_method1("set_state", (np_random_state,))?;
+    }
+    if let (Some(rng), Some(rng_state)) = (rng, rng_state) {
+        rng.call_method1("setstate", (rng_state,))?;
+    }
+    Ok(())
+}
+
+// ---- Interventions ----
+
+// When an intervention fires, checked at the start of every timestep (1 is
+// the first one) on the state it starts from
+#[derive(Debug)]
+pub enum Trigger<'a> {
+    At(usize),
+    Window(usize, usize), // Every timestep from the first to the second, included
+    // The first timestep a (state, params) -> bool callable returns true on, only
+    When(&'a PyAny),
+}
+
+// Changes to a run when its trigger fires, name is reported in the
+// RunReport. Policies (fns or their names) are switched off/on in every
+// substep they are in
+#[derive(Debug)]
+pub struct Intervention<'a> {
+    pub name: String,
+    pub trigger: Trigger<'a>,
+    pub set_state: Option<&'a PyDict>,
+    pub set_params: Option<&'a PyDict>,
+    pub enable_policies: Vec<&'a PyAny>,
+    pub disable_policies: Vec<&'a PyAny>,
+}
+
+impl<'a> Intervention<'a> {
+    // A dict: "name", a trigger ("at": timestep, "window": (first, last)
+    // timesteps or "when": callable) and actions ("set_state" and
+    // "set_params" dicts, "enable_policies" and "disable_policies" lists)
+    fn from_py(intervention: &'a PyAny) -> PyResult<Self> {
+        let intervention = intervention.downcast::<PyDict>()?;
+        let name = match intervention.get_item("name") {
+            Some(name) => name.extract::<String>()?,
+            None => return Err(PyValueError::new_err(format!("Intervention {} has no 'name'", repr(intervention)))),
+        };
+        let trigger = match (intervention.get_item("at"), intervention.get_item("window"), intervention.get_item("when")) {
+            (Some(at), None, None) => Trigger::At(at.extract()?),
+            (None, Some(window), None) => {
+                let (first, last) = window.extract()?;
+                Trigger::Window(first, last)
+            },
+            (None, None, Some(when)) if when.is_callable() => Trigger::When(when),
+            _ => return Err(PyValueError::new_err(format!(
+                "Intervention '{}' should have one trigger: 'at' (a timestep), 'window' (first and last \
+                timesteps) or 'when' (a (state, params) -> bool callable)", name
+            ))),
+        };
+        let dict = |key| -> PyResult<Option<&PyDict>> {
+            match intervention.get_item(key) {
+                Some(dict) => Ok(Some(dict.downcast::<PyDict>()?)),
+                None => Ok(None),
+            }
+        };
+        let policies = |key| match intervention.get_item(key) {
+            Some(policies) => policies.iter()?.collect::<PyResult<Vec<_>>>(),
+            None => Ok(Vec::new()),
+        };
+        Ok(Intervention {
+            trigger,
+            set_state: dict("set_state")?,
+            set_params: dict("set_params")?,
+            enable_policies: policies("enable_policies")?,
+            disable_policies: policies("disable_policies")?,
+            name,
+        })
+    }
+}
+
+fn is_policy(policy: &PyAny, target: &PyAny) -> bool {
+    policy.is(target) || target.extract::<&str>().map_or(false, |name| function_name(policy) == name)
+}
+
+// What the interventions did to a run, besides its state
+pub struct RunInterventions<'py> {
+    pub params: &'py Params, // Copied when changed
+    pub disabled_policies: std::collections::BTreeSet<(usize, usize)>, // (substep, policy) indices
+    pub triggered: std::collections::BTreeSet<usize>, // Interventions fired by a "when" trigger
+}
+
+// Fires the interventions due at the start of the step's timestep, in their
+// order. Returns the state to go on from: a copy of state if it is changed
+// but not reusable (see run_single_simulation)
+fn intervene<'py>(
+    cadcad_config: &cadCADConfig<'py>,
+    step: &StepContext,
+    state: &'py State,
+    is_reusable: bool,
+    run: &mut RunInterventions<'py>,
+    recorder: &mut Recorder<'py>
+) -> PyResult<&'py State> {
+    let mut state = state;
+    let mut is_reusable = is_reusable;
+    for (n, intervention) in cadcad_config.interventions.iter().enumerate() {
+        let is_due = match intervention.trigger {
+            Trigger::At(timestep) => step.timestep == timestep,
+            Trigger::Window(first, last) => (first..=last).contains(&step.timestep),
+            Trigger::When(predicate) => !run.triggered.contains(&n) && call_guarded(
+                cadcad_config.sim_config.debug, state, step, "Intervention trigger", predicate, || {
+                    predicate.call1((state_view(state.py(), state)?, run.params))
+                        .and_then(|is_met| is_met.is_true())
+                        .map_err(|err| simulation_error(step, "Intervention trigger", predicate, "failed", None, err))
+                }
+            )?,
+        };
+        if !is_due { continue; }
+        if let Trigger::When(_) = intervention.trigger { run.triggered.insert(n); }
+
+        if let Some(set_state) = intervention.set_state {
+            if !is_reusable {
+                state = state.copy()?;
+                is_reusable = true;
+            }
+            for (key, value) in set_state { state.set_item(key, value)?; }
+        }
+        if let Some(set_params) = intervention.set_params {
+            run.params = run.params.copy()?; // Those of the param subset are shared by its runs
+            for (key, value) in set_params { run.params.set_item(key, value)?; }
+        }
+        for (j, psub) in cadcad_config.partial_state_update_blocks.iter().enumerate() {
+            for (p, policy) in psub.policies.iter().enumerate() {
+                if intervention.disable_policies.iter().any(|target| is_policy(policy, target)) {
+                    run.disabled_policies.insert((j, p));
+                }
+                if intervention.enable_policies.iter().any(|target| is_policy(policy, target)) {
+                    run.disabled_policies.remove(&(j, p));
+                }
+            }
+        }
+        log(state.py(), LogLevel::Debug, || format!(
+            "--- Simulation {} of param subset {}: intervention '{}' at timestep {}",
+            step.run, step.subset, intervention.name, step.timestep
+        ));
+        recorder.interventions.push((step.timestep, intervention.name.clone()));
+    }
+    Ok(state)
+}
+
+// Where a policy/SUF is called, reported by SimulationError
+#[derive(Debug, Clone, Copy)]
+pub struct StepContext {
+    pub subset: usize,
+    pub run: usize,
+    pub timestep: usize,
+    pub substep: usize,
+}
+
+// Read-only view of a state (a mappingproxy), as passed to the Python
+// functions, so that they cannot rewrite the states of the trajectory
+fn state_view<'py>(py: Python<'py>, state: &'py PyAny) -> PyResult<&'py PyAny> {
+    unsafe { py.from_owned_ptr_or_err(pyo3::ffi::PyDictProxy_New(pyo3::AsPyPointer::as_ptr(state))) }
+}
+
+// Deep copy of a state before its Python functions are called, in debug
+// mode, to find those which mutate its values through the read-only view
+pub struct StateGuard<'py> {
+    state: &'py State,
+    snapshot: &'py PyDict,
+}
+
+impl<'py> StateGuard<'py> {
+    fn new(py: Python<'py>, state: &'py State) -> PyResult<Self> {
+        let snapshot = PyModule::import(py, "copy")?.call_method1("deepcopy", (state,))?;
+        Ok(StateGuard { state, snapshot: snapshot.downcast()? })
+    }
+
+    // A SimulationError if function changed the state
+    fn check(&self, step: &StepContext, kind: &str, function: &PyAny) -> PyResult<()> {
+        let py = function.py();
+        let eq = PyModule::import(py, "operator")?.getattr("eq")?;
+        let is_same = |a: &PyAny, b: &PyAny| a.is(b) || eq.call1((a, b))
+            .and_then(|is_same| is_same.is_true())
+            .unwrap_or_else(|_| repr(a) == repr(b)); // e.g. numpy arrays
+        let mut keys = Vec::new();
+        for (key, value) in self.snapshot {
+            if !self.state.get_item(key).map_or(false, |current| is_same(current, value)) { keys.push(key); }
+        }
+        for key in self.state.keys() {
+            if !self.snapshot.contains(key)? { keys.push(key); }
+        }
+        match keys.is_empty() {
+            true => Ok(()),
+            false => Err(state_mutation_error(py, step, kind, function, keys)),
+        }
+    }
+}
+
+// call (of function, on state), checked by a StateGuard in debug mode
+fn call_guarded<'py, T>(
+    debug: bool, state: &'py State, step: &StepContext, kind: &str, function: &PyAny,
+    call: impl FnOnce() -> PyResult<T>
+) -> PyResult<T> {
+    let guard = match debug {
+        true => Some(StateGuard::new(state.py(), state)?),
+        false => None,
+    };
+    let result = call()?;
+    if let Some(guard) = &guard { guard.check(step, kind, function)?; }
+    Ok(result)
+}
+
+// With a state history, the functions are called with cadCAD's signatures.
+// Policies return a (key, value) tuple, a dict of signals (as in cadCAD), a
+// list of (key, value) tuples or None (no signal)
 pub fn call_py_policy<'a>(
-    policy: &'a PyAny, current_state: &State
-) -> Signal<'a> {
-    let pyPolicy = policy.downcast::<PyFunction>().unwrap();
-    let result = pyPolicy.call1(
-        (current_state, 0)
-    ).unwrap().downcast::<PyTuple>().unwrap();
-    let key = to_string(result.get_item(0).unwrap());
-    let value = result.get_item(1).unwrap();  
-    Signal { key, value }
+    policy: &'a PyAny,
+    current_state: &PyAny, // See state_view
+    params: &Params,
+    rng: Option<&PyAny>,
+    history: Option<&PyAny>,
+    step: &StepContext
+) -> PyResult<Vec<Signal<'a>>> {
+    let result = match (history, rng) {
+        (Some(history), Some(rng)) => policy.call1((params, step.substep, history, current_state, rng)),
+        (Some(history), None) => policy.call1((params, step.substep, history, current_state)),
+        (None, Some(rng)) => policy.call1((current_state, params, rng)),
+        (None, None) => policy.call1((current_state, params)),
+    }.map_err(|err| simulation_error(step, "Policy", policy, "failed", None, err))?;
+    let to_signal = |(key, value): (&'a PyAny, &'a PyAny)| -> PyResult<Signal<'a>> {
+        Ok(Signal { key: key.extract::<String>()?, value })
+    };
+    let signals = if result.is_none() {
+        Ok(Vec::new())
+    } else if let Ok(signals) = result.downcast::<PyDict>() {
+        signals.iter().map(to_signal).collect()
+    } else if let Ok((key, value)) = result.extract::<(String, &PyAny)>() {
+        Ok(vec![Signal { key, value }])
+    } else if result.downcast::<PyList>().is_ok() || result.downcast::<PyTuple>().is_ok() {
+        result.iter()?.map(|pair| to_signal(pair?.extract()?)).collect()
+    } else {
+        Err(PyTypeError::new_err(format!("{} is not a signal", type_name(result))))
+    };
+    signals.map_err(|err| {
+        let problem = format!(
+            "returned {}, expected a (key, value) tuple, a dict, a list of (key, value) tuples or None",
+            repr(result)
+        );
+        simulation_error(step, "Policy", policy, &problem, Some(result), err)
+    })
 }
 
 pub fn call_py_state_update_fn<'a>(
     state_update_fn: &'a PyAny,
-    current_state: &State,
-    signals: &Signals
-) -> Update<'a> {
-    let pyfn = state_update_fn.downcast::<PyFunction>().unwrap();
-    let result = pyfn.call1(
-        (current_state, signals)
-    ).unwrap().downcast::<PyTuple>().unwrap();
-    let key = to_string(result.get_item(0).unwrap());
-    let value = result.get_item(1).unwrap();
-    Update { key, value }
+    current_state: &PyAny, // See state_view
+    signals: &Signals,
+    params: &Params,
+    history: Option<&PyAny>,
+    step: &StepContext
+) -> PyResult<Update<'a>> {
+    let result = match history {
+        Some(history) => state_update_fn.call1((params, step.substep, history, current_state, signals)),
+        None => state_update_fn.call1((current_state, signals, params)),
+    }.map_err(|err| simulation_error(step, "State update fn", state_update_fn, "failed", None, err))?;
+    let (key, value) = result.extract::<(String, &PyAny)>().map_err(|err| {
+        let problem = format!("returned {}, expected a (key, value) tuple", repr(result));
+        simulation_error(step, "State update fn", state_update_fn, &problem, Some(result), err)
+    })?;
+    Ok(Update { key, value })
+}
+
+// PyErr keeps the traceback aside of the exception object, so it is
+// attached here to be shown when the object is used as a __cause__
+fn to_exception_with_traceback(py: Python, err: PyErr) -> PyObject {
+    err.restore(py);
+    unsafe {
+        let mut ptype = std::ptr::null_mut();
+        let mut pvalue = std::ptr::null_mut();
+        let mut ptraceback = std::ptr::null_mut();
+        pyo3::ffi::PyErr_Fetch(&mut ptype, &mut pvalue, &mut ptraceback);
+        pyo3::ffi::PyErr_NormalizeException(&mut ptype, &mut pvalue, &mut ptraceback);
+        if !ptraceback.is_null() {
+            pyo3::ffi::PyException_SetTraceback(pvalue, ptraceback);
+        }
+        pyo3::ffi::Py_XDECREF(ptype);
+        pyo3::ffi::Py_XDECREF(ptraceback);
+        PyObject::from_owned_ptr(py, pvalue)
+    }
+}
+
+// Sets the __cause__ of err, as `raise err from cause` would
+fn with_cause(py: Python, err: PyErr, cause: PyErr) -> PyErr {
+    let _todo = err.to_object(py).setattr(py, "__cause__", to_exception_with_traceback(py, cause));
+    err
+}
+
+// SimulationError for a failed policy/SUF call, with the call's context as
+// attributes and the original error (and traceback) as __cause__. Errors
+// which are not Exceptions (e.g. KeyboardInterrupt) are not run failures,
+// they are returned as is
+fn simulation_error(
+    step: &StepContext,
+    kind: &str,
+    function: &PyAny,
+    problem: &str,
+    returned: Option<&PyAny>,
+    cause: PyErr
+) -> PyErr {
+    let py = function.py();
+    if !is_exception(py, &cause) { return cause; }
+    let name = function_name(function);
+    let err = SimulationError::new_err(format!(
+        "{} '{}' {} (run {}, timestep {}, substep {}): {}",
+        kind, name, problem, step.run, step.timestep, step.substep, cause
+    ));
+    let exc = set_step_attrs(py, &err, step);
+    let _todo = exc.setattr(py, "function", name);
+    let _todo = exc.setattr(py, "returned", returned);
+    with_cause(py, err, cause)
+}
+
+fn is_exception(py: Python, err: &PyErr) -> bool {
+    err.matches(py, py.get_type::<PyException>())
+}
+
+// SimulationError for a state key without a state update fn, in strict
+// state keys mode
+fn undeclared_state_key_error(py: Python, step: &StepContext, key: &PyAny) -> PyErr {
+    let err = SimulationError::new_err(format!(
+        "State key {} has no state update fn (run {}, timestep {}, substep {})",
+        repr(key), step.run, step.timestep, step.substep
+    ));
+    let exc = set_step_attrs(py, &err, step);
+    let _todo = exc.setattr(py, "key", key);
+    err
+}
+
+// SimulationError for a state update fn returning an update of another
+// key than the one it is declared for
+fn update_key_mismatch_error(
+    py: Python, step: &StepContext, function: &PyAny, declared: &str, key: &str
+) -> PyErr {
+    let name = function_name(function);
+    let err = SimulationError::new_err(format!(
+        "State update fn '{}' declared for key '{}' returned an update of key '{}' (run {}, timestep {}, substep {})",
+        name, declared, key, step.run, step.timestep, step.substep
+    ));
+    let exc = set_step_attrs(py, &err, step);
+    let _todo = exc.setattr(py, "function", name);
+    let _todo = exc.setattr(py, "declared", declared);
+    let _todo = exc.setattr(py, "key", key);
+    err
+}
+
+fn state_mutation_error(py: Python, step: &StepContext, kind: &str, function: &PyAny, keys: Vec<&PyAny>) -> PyErr {
+    let name = function_name(function);
+    let err = SimulationError::new_err(format!(
+        "{} '{}' mutated state key(s) {}, states are read-only (run {}, timestep {}, substep {})",
+        kind, name, keys.iter().map(|key| repr(key)).collect::<Vec<_>>().join(", "),
+        step.run, step.timestep, step.substep
+    ));
+    let exc = set_step_attrs(py, &err, step);
+    let _todo = exc.setattr(py, "function", name);
+    let _todo = exc.setattr(py, "keys", keys);
+    err
+}
+
+fn set_step_attrs(py: Python, err: &PyErr, step: &StepContext) -> PyObject {
+    let exc = err.to_object(py);
+    let _todo = exc.setattr(py, "subset", step.subset);
+    let _todo = exc.setattr(py, "run", step.run);
+    let _todo = exc.setattr(py, "timestep", step.timestep);
+    let _todo = exc.setattr(py, "substep", step.substep);
+    exc
+}
+
+// A problem found by validate. Errors are mistakes in the state keys of
+// the updates, a Simulation is not created with them. Warnings may be
+// intended, e.g. a type change
+pub struct ValidationIssue {
+    pub problem: String,
+    pub is_error: bool,
+}
+
+impl ValidationIssue {
+    fn error(problem: String) -> Self {
+        ValidationIssue { problem, is_error: true }
+    }
+
+    fn warning(problem: String) -> Self {
+        ValidationIssue { problem, is_error: false }
+    }
+}
+
+fn error_message(py: Python, err: PyErr) -> String {
+    err.to_object(py).as_ref(py).to_string()
+}
+
+// Checks a config before running it: a checkpoint with a sink, then a dry
+// run of each substep on the initial state (first param subset), with states and signals traced to
+// report signals consumed but not produced (and vice versa), state keys
+// read but missing, updates of keys not in init_state, of a key updated
+// by another state update fn or other than the declared one, state updates
+// changing the type of a value, and policy/SUF failures (e.g. results that
+// are not (key, value) tuples)
+pub fn validate(cadcad_config: &cadCADConfig) -> PyResult<Vec<ValidationIssue>> {
+    let py = cadcad_config.init_state.py();
+    let tracing_dict = PyModule::from_code(py, TRACING_DICT, "tracing_dict.py", "tracing_dict")?
+        .getattr("TracingDict")?;
+    let sim_config = &cadcad_config.sim_config;
+    let params = param_subsets(py, sim_config.params)?[0];
+    let rng = match sim_config.policy_rng {
+        true => Some(PyModule::import(py, "random")?.getattr("Random")?.call1((0,))?),
+        false => None,
+    };
+    let sorted_reprs = |keys: &PyAny| -> PyResult<Vec<String>> {
+        let mut keys = keys.iter()?.map(|key| key.map(repr)).collect::<PyResult<Vec<_>>>()?;
+        keys.sort();
+        Ok(keys)
+    };
+
+    let mut issues = Vec::new();
+    if cadcad_config.checkpoint.is_some() && cadcad_config.sink.is_some() {
+        // Sinks cannot take back the states written after a checkpoint
+        issues.push(ValidationIssue::error("checkpoint cannot be used with a sink".to_string()));
+    }
+    let mut state = cadcad_config.init_state.copy()?;
+    add_additional_init_state_keys(state, 0, 0);
+    let history = match sim_config.cadcad_signatures {
+        true => Some(new_state_history(py, sim_config, PyList::new(py, vec![vec![state]]), 1)?),
+        false => None,
+    };
+    for (j, psub) in cadcad_config.partial_state_update_blocks.iter().enumerate() {
+        let step = StepContext { subset: 0, run: 1, timestep: 1, substep: j+1 };
+        let traced_state = tracing_dict.call1((state,))?;
+        let state_view = state_view(py, traced_state)?;
+
+        // a. Policies
+        let signals = Signals::new(py);
+        for policy in psub.policies {
+            let outcome = call_py_policy(policy, state_view, params, rng, history, &step)
+                .and_then(|policy_signals| policy_signals.into_iter().try_for_each(
+                    |signal| aggregate_signal(cadcad_config, signals, signal, policy, &step)
+                ));
+            if let Err(err) = outcome { issues.push(ValidationIssue::warning(error_message(py, err))); }
+        }
+        let traced_signals = tracing_dict.call1((signals,))?;
+
+        // b. State update fns
+        let new_state = match sim_config.strict_state_keys {
+            true => State::new(py),
+            false => state.copy()?,
+        };
+        let mut updated_keys = std::collections::HashSet::new();
+        for (u, state_update_fn) in psub.variables.iter().enumerate() {
+            let update = match call_py_state_update_fn(
+                state_update_fn, state_view, traced_signals.downcast()?, params, history, &step
+            ) {
+                Ok(update) => update,
+                Err(err) => { issues.push(ValidationIssue::warning(error_message(py, err))); continue; },
+            };
+            if let Some(key) = psub.keys.as_ref().map(|keys| &keys[u]).filter(|key| **key != update.key) {
+                let err = update_key_mismatch_error(py, &step, state_update_fn, key, &update.key);
+                issues.push(ValidationIssue::error(error_message(py, err)));
+            }
+            if !updated_keys.insert(update.key.clone()) {
+                issues.push(ValidationIssue::error(format!(
+                    "Several state update fns update key '{}' (substep {})", update.key, j+1
+                )));
+            }
+            if !state.contains(&update.key)? {
+                issues.push(ValidationIssue::error(format!(
+                    "State update fn '{}' updates key '{}', which is not in init_state (substep {})",
+                    function_name(state_update_fn), update.key, j+1
+                )));
+            }
+            if let Some(value) = state.get_item(&update.key) {
+                if !value.get_type().is(update.value.get_type()) {
+                    issues.push(ValidationIssue::warning(format!(
+                        "State update fn '{}' changes the type of '{}' from {} to {} (substep {})",
+                        function_name(state_update_fn), update.key, type_name(value), type_name(update.value), j+1
+                    )));
+                }
+            }
+            new_state.set_item(update.key, update.value)?;
+        }
+
+        // c. Traced keys
+        for key in sorted_reprs(traced_state.getattr("missing")?)? {
+            issues.push(ValidationIssue::warning(format!(
+                "State key {} is read but missing from the state (substep {})", key, j+1
+            )));
+        }
+        for key in sorted_reprs(traced_signals.getattr("missing")?)? {
+            issues.push(ValidationIssue::warning(format!(
+                "Signal {} is consumed but no policy produces it (substep {})", key, j+1
+            )));
+        }
+        let read_signals = traced_signals.getattr("read")?;
+        for key in signals.keys() {
+            if !read_signals.contains(key)? {
+                issues.push(ValidationIssue::warning(format!(
+                    "Signal {} is produced but no state update fn consumes it (substep {})", repr(key), j+1
+                )));
+            }
+        }
+        if sim_config.strict_state_keys {
+            for key in state.keys() {
+                if !is_cadcad_key(key) && !new_state.contains(key)? {
+                    issues.push(ValidationIssue::warning(format!(
+                        "State key {} has no state update fn (substep {})", repr(key), j+1
+                    )));
+                }
+            }
+        }
+
+        add_additional_new_state_keys(new_state, 0, 0, j, 0);
+        if let Some(history) = history { history.call_method1("_push", (new_state, j == 0))?; }
+        state = new_state;
+    }
+    Ok(issues)
 }
 
 // Pyo3 utility fns.
-fn get_usize(dic: &PyDict, key: &str) -> usize {
-    to_usize(dic.get_item(key).unwrap())
+fn get_usize(dic: &PyDict, key: &str) -> PyResult<usize> {
+    let any = dic.get_item(key).ok_or_else(||
+        PyKeyError::new_err(format!("sim_config has no '{}' key", key))
+    )?;
+    any.extract::<usize>().map_err(|_| PyValueError::new_err(format!(
+        "sim_config '{}' should be a non-negative int, got {}", key, repr(any)
+    )))
+}
+
+fn function_name(function: &PyAny) -> String {
+    function.getattr("__name__")
+        .and_then(|name| name.extract::<String>())
+        .unwrap_or_else(|_| repr(function))
+}
+
+fn type_name(any: &PyAny) -> String {
+    any.get_type().getattr("__name__")
+        .and_then(|name| name.extract::<String>())
+        .unwrap_or_else(|_| "<unknown>".to_string())
+}
+
+fn repr(any: &PyAny) -> String {
+    any.repr().map_or_else(|_| "<unprintable>".to_string(), |repr| repr.to_string())
 }
 
-fn to_usize(any: &PyAny) -> usize {
-    any.downcast::<PyInt>().unwrap().extract::<usize>().unwrap()
+fn get_list<'a>(dic: &'a PyDict, key: &str) -> PyResult<&'a PyList> {
+    let any = dic.get_item(key).ok_or_else(|| 
+        PyKeyError::new_err(format!("Partial state update block has no '{}' key", key))
+    )?;
+    Ok(any.downcast::<PyList>()?)
 }
 
-fn to_string(any: &PyAny) -> String {
-    any.downcast::<PyString>().unwrap().extract::<String>().unwrap()
+// variables: a list of state update fns, or a dict of them by the state
+// key they update
+fn to_partial_state_update_blocks(psubs: &PyList) -> PyResult<Vec<PartialStateUpdateBlock<'_>>> {
+    psubs.iter().map(|psub| {
+        let psub = psub.downcast::<PyDict>()?;
+        let (variables, keys) = match psub.get_item("variables").map(|variables| variables.downcast::<PyDict>()) {
+            Some(Ok(variables)) => (variables.values(), Some(variables.keys().extract::<Vec<String>>()?)),
+            _ => (get_list(psub, "variables")?, None),
+        };
+        Ok(PartialStateUpdateBlock {
+            policies: get_list(psub, "policies")?,
+            variables,
+            keys,
+        })
+    }).collect()
 }
 
+// Config checks, before any run: state update fns are declared for keys
+// of init_state (ValueError)
+fn check_state_keys(cadcad_config: &cadCADConfig) -> PyResult<()> {
+    for (j, psub) in cadcad_config.partial_state_update_blocks.iter().enumerate() {
+        for key in psub.keys.iter().flatten() {
+            if !cadcad_config.init_state.contains(key)? {
+                return Err(PyValueError::new_err(format!(
+                    "State update fn of substep {} declared for key '{}', which is not in init_state", j+1, key
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Printed, not logged: print_trajectory asks for the output whatever the
+// logging config
 fn print_trajectory(trajectory: &Trajectory) {
     println!("--- Trajectory:");
     for (i, state) in trajectory.iter().enumerate() {
@@ -89,139 +1396,1255 @@ fn print_trajectory(trajectory: &Trajectory) {
     }
 }
 
-fn print_stats(trajectory: &Trajectory) {
-    let size_of_state = std::mem::size_of::<State>();
-    println!("--- Size of State obj.: {:?}", size_of_state);
-    println!("--- Size of trajectory obj.: {}", std::mem::size_of_val(&*trajectory));
+// Estimate of the bytes taken by a state: the dict and its values, as per
+// sys.getsizeof (values shared between states are counted in each)
+fn state_memory(py: Python, state: &State) -> PyResult<usize> {
+    let getsizeof = PyModule::import(py, "sys")?.getattr("getsizeof")?;
+    let mut memory = getsizeof.call1((state,))?.extract::<usize>()?;
+    for value in state.values() {
+        memory += getsizeof.call1((value,))?.extract::<usize>()?;
+    }
+    Ok(memory)
+}
+
+// Expands the params into param subsets the same way cadCAD does: subset s
+// takes the s-th item of every list value, lists of a single item keep it
+// and lists of other lengths raise a ValueError
+fn param_subsets<'a>(py: Python<'a>, params: &PyDict) -> PyResult<Vec<&'a Params>> {
+    let n_subset = params.values().iter()
+        .map(|value| value.downcast::<PyList>().map_or(1, |values| values.len()))
+        .max().unwrap_or(1).max(1);
+    for (key, value) in params {
+        match value.downcast::<PyList>() {
+            Ok(values) if values.len() > 1 && values.len() != n_subset => {
+                return Err(PyValueError::new_err(format!(
+                    "Param '{}' has {} values, sweeps should have 1 or {} values", key, values.len(), n_subset
+                )));
+            },
+            _ => {},
+        }
+    }
+    (0..n_subset).map(|s| {
+        let subset = Params::new(py);
+        for (key, value) in params {
+            match value.downcast::<PyList>() {
+                Ok(values) if !values.is_empty() => {
+                    subset.set_item(key, values.get_item(s.min(values.len()-1) as isize))?;
+                },
+                _ => subset.set_item(key, value)?,
+            }
+        }
+        Ok(subset)
+    }).collect()
+}
+
+fn splitmix64(z: u64) -> u64 {
+    let z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    let z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// Seed of run i of param subset s, so a run does not depend on which
+// process or in which order it is executed
+pub fn run_seed(master_seed: u64, s: usize, i: usize) -> u64 {
+    splitmix64(splitmix64(master_seed.wrapping_add(s as u64)).wrapping_add(i as u64))
+}
+
+// Seeds Python's global `random` and, if importable, `numpy.random`
+fn seed_py_random(py: Python, seed: u64) -> PyResult<()> {
+    PyModule::import(py, "random")?.call_method1("seed", (seed,))?;
+    if let Ok(np_random) = PyModule::import(py, "numpy.random") {
+        np_random.call_method1("seed", (seed & 0xFFFF_FFFF,))?; // 32 bits seeds only
+    }
+    Ok(())
 }
 
-fn add_additional_init_state_keys(init_state: &State, i: usize) {
+// All (param subset, run) pairs, in result data order
+fn all_runs(n_subset: usize, n_run: usize) -> Vec<(usize, usize)> {
+    (0..n_subset).flat_map(|s| (0..n_run).map(move |i| (s, i))).collect()
+}
+
+fn add_additional_init_state_keys(init_state: &State, s: usize, i: usize) {
+    let _todo = init_state.set_item("subset", s);
     let _todo = init_state.set_item("run", i+1);
     let _todo = init_state.set_item("substep", 0);
     let _todo = init_state.set_item("timestep", 0);
 }
 
-fn add_additional_new_state_keys(new_state: &State, i: usize, k: usize) {
+fn add_additional_new_state_keys(new_state: &State, s: usize, i: usize, j: usize, k: usize) {
+    let _todo = new_state.set_item("subset", s);
     let _todo = new_state.set_item("run", i+1);
-    let _todo = new_state.set_item("substep", 1);
+    let _todo = new_state.set_item("substep", j+1);
     let _todo = new_state.set_item("timestep", k+1);
 }
 
-// Todo: Refactor this fn, remove unnecessary prints after POC period
-fn run_simulation_impl(cadcad_config: &cadCADConfig) -> Vec<Vec<PyObject>> {
-    let gil = Python::acquire_gil(); // Acquires the global interpreter lock, 
-    let py = gil.python();           // allowing access to the Python interpreter.
+const CADCAD_KEYS: [&str; 4] = ["subset", "run", "substep", "timestep"];
 
-    println!("\n----------------------------------------------");
-    println!("\n### Project: {} ...", &cadcad_config.name);
+fn is_cadcad_key(key: &PyAny) -> bool {
+    key.extract::<&str>().map_or(false, |key| CADCAD_KEYS.contains(&key))
+}
 
-    let module = PyModule::import(py, "operator").unwrap();
-    let py_add = module.getattr("add").unwrap();
+// Records the states of runs as per the SimConfig's recording options, in
+// the run's trajectory and the sink. Also keeps the last state of the run
+pub struct Recorder<'py> {
+    pub record_stride: usize,
+    pub record_keys: Option<Vec<String>>,
+    pub final_state_only: bool,
+    pub keep_trajectory: bool,
+    pub sink: Option<StateSink<'py>>,
+    pub trajectory: Trajectory<'py>,
+    pub last_state: &'py State,
+    // Of the current run, for its RunReport
+    pub steps: usize,
+    pub states_recorded: usize,
+    pub stop: Option<(usize, String)>,
+    pub interventions: Vec<(usize, String)>,
+}
 
-    // Final/result data set of simulation
-    let mut result_data = Vec::<Vec<PyObject>>::new();
+impl<'py> Recorder<'py> {
+    pub fn new(cadcad_config: &cadCADConfig<'py>) -> PyResult<Self> {
+        let sim_config = &cadcad_config.sim_config;
+        Ok(Recorder {
+            record_stride: sim_config.record_stride,
+            record_keys: sim_config.record_keys.clone(),
+            final_state_only: sim_config.final_state_only,
+            keep_trajectory: cadcad_config.keep_trajectories,
+            sink: match &cadcad_config.sink {
+                Some(path) => Some(StateSink::create(cadcad_config.init_state.py(), path)?),
+                None => None,
+            },
+            trajectory: Trajectory::new(),
+            last_state: cadcad_config.init_state,
+            steps: 0,
+            states_recorded: 0,
+            stop: None,
+            interventions: Vec::new(),
+        })
+    }
+
+    fn is_recorded(&self, timestep: usize) -> bool {
+        !self.final_state_only && timestep % self.record_stride == 0
+    }
+
+    // Returns false if the state itself is kept (in the trajectory or by the
+    // sink), true if it can be reused
+    fn record(&mut self, state: &'py State) -> PyResult<bool> {
+        self.states_recorded += 1;
+        let recorded = match &self.record_keys {
+            Some(keys) => {
+                let recorded = State::new(state.py());
+                for (key, value) in state {
+                    if is_cadcad_key(key) || key.extract::<&str>().map_or(false, |key| keys.iter().any(|k| k == key)) {
+                        recorded.set_item(key, value)?;
+                    }
+                }
+                recorded
+            },
+            None => state,
+        };
+        if let Some(sink) = &mut self.sink { sink.write_state(recorded)?; }
+        if self.keep_trajectory { self.trajectory.push(recorded); }
+        let is_kept = self.keep_trajectory || self.sink.as_ref().map_or(false, StateSink::keeps_states);
+        Ok(self.record_keys.is_some() || !is_kept)
+    }
+}
+
+// Runs a single Monte Carlo run (i) of a param subset (s), its trajectory
+// filled in the recorder as it goes, so that it is kept up to a failure.
+// States neither recorded nor in the state history are reused for the next
+// ones. The caller's init_state is copied, not changed
+#[allow(clippy::too_many_arguments)]
+fn run_single_simulation<'py>(
+    py: Python<'py>,
+    cadcad_config: &cadCADConfig<'py>,
+    params: &'py Params,
+    rng: Option<&PyAny>,
+    s: usize,
+    i: usize,
+    recorder: &mut Recorder<'py>,
+    progress: &mut Option<&mut Progress>,
+    checkpointer: Option<&Checkpointer<'py>>
+) -> PyResult<()> {
+    let init_state = cadcad_config.init_state.copy()?;
+    add_additional_init_state_keys(init_state, s, i);
+    recorder.trajectory = Trajectory::new();
+    recorder.last_state = init_state;
+    recorder.steps = 0;
+    recorder.states_recorded = 0;
+    recorder.stop = None;
+    recorder.interventions = Vec::new();
+
+    let mut current_state = init_state;
+    let mut start = 0;
+    let mut steady_timesteps = 0;
+    let mut interventions = RunInterventions {
+        params, disabled_policies: Default::default(), triggered: Default::default(),
+    };
     let sim_config = &cadcad_config.sim_config;
-    for i in 0..sim_config.n_run { // Simulation
-        println!("\n--- \n Starting simulation {} ...", i);
-        println!("---");
-        // 1. Display sim. config.
-        println!("--- SIM_CONFIG: {:?}", sim_config);
-
-        let now = std::time::Instant::now(); // Perf. diag.
-        // 2. Create trajectory
-        let init_state = cadcad_config.init_state;
-        let mut trajectory = vec![init_state];
-        add_additional_init_state_keys(init_state, i);
-        let mut trajectory_of_state_ptrs = vec![init_state.copy().unwrap().into()];
-
-        for k in 0..sim_config.timesteps { // Experiment
-            let current_state = &trajectory[k];
-            let new_state = State::new(py);
+    let mut history = match sim_config.cadcad_signatures {
+        true => Some(new_state_history(py, sim_config, PyList::new(py, vec![vec![init_state]]), 1)?),
+        false => None,
+    };
+    let (mut run_file, resumed) = match checkpointer {
+        Some(checkpointer) => {
+            let (run_file, resumed) = checkpointer.run_file(py, s, i)?;
+            (Some(run_file), resumed)
+        },
+        None => (None, None),
+    };
+    match resumed {
+        Some(run) => {
+            start = checkpoint_item(run, "timestep")?.extract::<usize>()?;
+            current_state = checkpoint_item(run, "state")?.downcast::<PyDict>()?;
+            recorder.trajectory = checkpoint_item(run, "trajectory")?.extract::<Trajectory>()?;
+            recorder.states_recorded = checkpoint_item(run, "states_recorded")?.extract::<usize>()?;
+            steady_timesteps = checkpoint_item(run, "steady_timesteps")?.extract::<usize>()?;
+            interventions = RunInterventions {
+                params: checkpoint_item(run, "params")?.downcast::<PyDict>()?,
+                disabled_policies: checkpoint_item(run, "disabled_policies")?.extract::<Vec<(usize, usize)>>()?
+                    .into_iter().collect(),
+                triggered: checkpoint_item(run, "triggered")?.extract::<Vec<usize>>()?.into_iter().collect(),
+            };
+            recorder.interventions = checkpoint_item(run, "interventions")?.extract()?;
+            if history.is_some() {
+                let (timesteps, length) = checkpoint_item(run, "history")?.extract::<(&PyAny, usize)>()?;
+                history = Some(new_state_history(py, sim_config, timesteps, length)?);
+            }
+            recorder.steps = start * cadcad_config.partial_state_update_blocks.len();
+            recorder.last_state = current_state;
+            set_random_states(py, checkpoint_item(run, "random_states")?, rng)?;
+            if let Some(progress) = progress { progress.report(py, start)?; }
+        },
+        None => if recorder.is_recorded(0) { recorder.record(init_state)?; },
+    }
+
+    let mut is_reusable = false; // init_state and the checkpoint's may be recorded
+    let mut spare_state: Option<&State> = None;
+    // State at the end of the previous timestep, for steady state detection
+    let previous_state = State::new(py);
+    if sim_config.steady_state.is_some() { previous_state.call_method1("update", (current_state,))?; }
+    let mut last_timestep = sim_config.timesteps;
+    for k in start..sim_config.timesteps { // Experiment
+        let n_psub = cadcad_config.partial_state_update_blocks.len();
+        if !cadcad_config.interventions.is_empty() {
+            let step = StepContext { subset: s, run: i+1, timestep: k+1, substep: 0 };
+            let state = intervene(cadcad_config, &step, current_state, is_reusable, &mut interventions, recorder)?;
+            if !state.is(current_state) {
+                current_state = state;
+                recorder.last_state = state;
+                is_reusable = true;
+            }
+        }
+        let params = interventions.params;
+        for (j, psub) in cadcad_config.partial_state_update_blocks.iter().enumerate() { // Substep
+            // State keys without a state update fn are carried forward
+            let new_state = match (cadcad_config.sim_config.strict_state_keys, spare_state.take()) {
+                (true, Some(spare_state)) => { spare_state.clear(); spare_state },
+                (true, None) => State::new(py),
+                (false, Some(spare_state)) => {
+                    spare_state.call_method1("update", (current_state,))?;
+                    if spare_state.len() != current_state.len() {
+                        spare_state.clear();
+                        spare_state.call_method1("update", (current_state,))?;
+                    }
+                    spare_state
+                },
+                (false, None) => current_state.copy()?,
+            };
+            let step = StepContext { subset: s, run: i+1, timestep: k+1, substep: j+1 };
+            let state_view = state_view(py, current_state)?;
 
             // a. Apply policies
             let signals = Signals::new(py);
-            for policy in cadcad_config.policies {
-                let signal = call_py_policy(policy, current_state);
-                // i. Add to the existing signal (to enable multiple Python
-                //    policies for the same key writeable)
-                if signals.contains(&signal.key).unwrap() {
-                    let current_val = signals.get_item(&signal.key).unwrap();
-                    let sum = py_add.call1( (current_val, signal.value) ).unwrap();
-                    signals.set_item(&signal.key, sum)
-                        .map_err(|err| println!("{:?}", err)).ok();
-                }
-                // ii. Insert a new signal
-                else {
-                    signals.set_item(&signal.key, signal.value)
-                        .map_err(|err| println!("{:?}", err)).ok();
+            for (p, policy) in psub.policies.iter().enumerate() {
+                if interventions.disabled_policies.contains(&(j, p)) { continue; }
+                let policy_signals = call_guarded(sim_config.debug, current_state, &step, "Policy", policy, || {
+                    call_py_policy(policy, state_view, params, rng, history, &step)
+                })?;
+                for signal in policy_signals {
+                    aggregate_signal(cadcad_config, signals, signal, policy, &step)?;
                 }
             }
 
             // b. Apply state update fns
-            for state_update_fn in cadcad_config.state_update_functions {
-                let update = call_py_state_update_fn(
-                    state_update_fn, current_state, &signals
-                );
-                new_state.set_item(update.key, update.value)
-                    .map_err(|err| println!("{:?}", err)).ok();
+            for (u, state_update_fn) in psub.variables.iter().enumerate() {
+                let update = call_guarded(
+                    sim_config.debug, current_state, &step, "State update fn", state_update_fn,
+                    || call_py_state_update_fn(state_update_fn, state_view, &signals, params, history, &step)
+                )?;
+                if let Some(key) = psub.keys.as_ref().map(|keys| &keys[u]).filter(|key| **key != update.key) {
+                    return Err(update_key_mismatch_error(py, &step, state_update_fn, key, &update.key));
+                }
+                new_state.set_item(update.key, update.value)?;
+            }
+            if cadcad_config.sim_config.strict_state_keys {
+                for key in current_state.keys() {
+                    if !is_cadcad_key(key) && !new_state.contains(key)? {
+                        return Err(undeclared_state_key_error(py, &step, key));
+                    }
+                }
             }
 
-            add_additional_new_state_keys(new_state, i, k);
-            trajectory.push(new_state);
-            trajectory_of_state_ptrs.push(new_state.into());
+            add_additional_new_state_keys(new_state, s, i, j, k);
+            if is_reusable { spare_state = Some(current_state); }
+            current_state = new_state;
+            recorder.last_state = current_state;
+            recorder.steps += 1;
+            is_reusable = true;
+            if recorder.is_recorded(k+1) { is_reusable = recorder.record(current_state)?; }
+            if let Some(history) = history {
+                history.call_method1("_push", (current_state, j == 0))?;
+                is_reusable = false; // Views of it may be kept
+            }
         }
 
-        // x. Perf. Diagnostics
-        let elapsed = now.elapsed();
-        println!("--- End of simulation {:?}", i);
-        println!("--- Simulation time: {:.2?}", elapsed);
+        // Early termination
+        let step = StepContext { subset: s, run: i+1, timestep: k+1, substep: n_psub };
+        for condition in &sim_config.stop_conditions {
+            let is_met = call_guarded(sim_config.debug, current_state, &step, "Stop condition", condition.func, || {
+                condition.func.call1((state_view(py, current_state)?, params))
+                    .and_then(|is_met| is_met.is_true())
+                    .map_err(|err| simulation_error(&step, "Stop condition", condition.func, "failed", None, err))
+            })?;
+            if is_met {
+                recorder.stop = Some((k+1, condition.name.clone()));
+                break;
+            }
+        }
+        if let Some(steady_state) = &sim_config.steady_state {
+            steady_timesteps = match steady_state.is_unchanged(previous_state, current_state)? {
+                true => steady_timesteps + 1,
+                false => 0,
+            };
+            if recorder.stop.is_none() && steady_timesteps >= steady_state.window {
+                recorder.stop = Some((k+1, "steady state".to_string()));
+            }
+            previous_state.clear();
+            previous_state.call_method1("update", (current_state,))?;
+        }
+        if recorder.stop.is_some() {
+            last_timestep = k+1;
+            break;
+        }
 
-        // 3. Stats
-        print_stats(&trajectory);
+        py.check_signals()?; // A KeyboardInterrupt stops the run promptly (main thread only)
+        py.allow_threads(|| {}); // Other threads may run between timesteps, e.g. run_async's caller
+        if let Some(progress) = progress { progress.timestep_done(py, k+1)?; }
+        if let (Some(checkpointer), Some(run_file)) = (checkpointer, &mut run_file) {
+            if checkpointer.is_due(k+1, cadcad_config.sim_config.timesteps) {
+                checkpointer.save_run(
+                    py, run_file, k+1, current_state, recorder, steady_timesteps, &interventions, history, rng
+                )?;
+            }
+        }
+    }
+    // The state a run stopped at is recorded whatever the stride
+    if !recorder.is_recorded(last_timestep) && (recorder.final_state_only || recorder.stop.is_some()) {
+        recorder.record(current_state)?;
+    }
+    if !recorder.keep_trajectory { recorder.trajectory.push(current_state); }
+    Ok(())
+}
 
-        // 4. Print trajectory
-        if cadcad_config.print_trajectory { print_trajectory(&trajectory); }
+fn aggregate_signal(
+    cadcad_config: &cadCADConfig,
+    signals: &Signals,
+    signal: Signal,
+    policy: &PyAny,
+    step: &StepContext
+) -> PyResult<()> {
+    // i. Aggregate with the existing signal (to enable multiple
+    //    Python policies for the same key writeable)
+    if let Some(current_val) = signals.get_item(&signal.key) {
+        let aggregation = cadcad_config.signal_aggregation
+            .get(&signal.key).unwrap_or(&cadcad_config.default_aggregation);
+        let aggregated = match aggregation {
+            Aggregation::LastWriterWins => signal.value,
+            Aggregation::Reduce { name, func } => {
+                func.call1( (current_val, signal.value) ).map_err(|err| {
+                    let problem = format!(
+                        "returned a '{}' signal that cannot be aggregated with the previous ones using '{}'",
+                        signal.key, name
+                    );
+                    simulation_error(step, "Policy", policy, &problem, Some(signal.value), err)
+                })?
+            },
+        };
+        signals.set_item(&signal.key, aggregated)?;
+    }
+    // ii. Insert a new signal
+    else {
+        signals.set_item(&signal.key, signal.value)?;
+    }
+    Ok(())
+}
 
-        result_data.push(trajectory_of_state_ptrs);
+// Record of a failed run: its subset, run, the last timestep it reached
+// and the error
+fn failed_run(py: Python, last_state: &State, err: PyErr) -> PyResult<PyObject> {
+    let record = PyDict::new(py);
+    for key in ["subset", "run", "timestep"] {
+        record.set_item(key, last_state.get_item(key))?;
     }
-    println!("\n------------------ END of Simulation ---------------------\n");
+    record.set_item("error", err.to_object(py))?;
+    Ok(record.into())
+}
+
+// Runs the (param subset, run) pairs. A run stopped by an error which is
+// not an Exception (e.g. KeyboardInterrupt) stops the simulation, whatever
+// the error policy: the results then have the runs done so far, this one
+// included, and the error as interrupted. With a checkpoint, the outputs of
+// the runs done before it are taken from it
+fn run_simulation_impl(
+    cadcad_config: &cadCADConfig, runs: &[(usize, usize)], mut progress: Option<&mut Progress>
+) -> PyResult<ResultData> {
+    let gil = Python::acquire_gil(); // Acquires the global interpreter lock, 
+    let py = gil.python();           // allowing access to the Python interpreter.
+
+    log(py, LogLevel::Info, || format!("### Project: {} ...", cadcad_config.name));
+
+    // Final/result data set of simulation
+    let mut result_data = Vec::<Vec<PyObject>>::new();
+    let mut failed_runs = Vec::<PyObject>::new();
+    let mut run_reports = Vec::<PyObject>::new();
+    let mut interrupted = None;
+    let sim_config = &cadcad_config.sim_config;
+    let subsets = param_subsets(py, sim_config.params)?;
+    let checkpointer = Checkpointer::create(py, cadcad_config, runs, subsets.len())?;
+    let mut recorder = Recorder::new(cadcad_config)?;
+    // The sink is closed whatever the outcome, with the states written so far
+    let outcome = (|| -> PyResult<()> {
+        for &(s, i) in runs { // Simulation
+            if let Some(run) = checkpointer.as_ref().map(|checkpointer| checkpointer.done_run(s, i)).transpose()?.flatten() {
+                let trajectory = checkpoint_item(run, "trajectory")?;
+                if !trajectory.is_none() { result_data.push(trajectory.extract()?); }
+                let failed_run = checkpoint_item(run, "failed_run")?;
+                if !failed_run.is_none() { failed_runs.push(failed_run.into()); }
+                run_reports.push(checkpoint_item(run, "report")?.into());
+                if let Some(progress) = &mut progress { progress.runs_done(py, 1)?; }
+                continue;
+            }
+            let params = subsets[s];
+            // Seed Python's randomness, and create the run's own `random.Random`
+            let seed = sim_config.seed.map(|master_seed| run_seed(master_seed, s, i));
+            // 1. Log sim. config., params and seed
+            log(py, LogLevel::Debug, || format!(
+                "--- Simulation {} of param subset {}: SIM_CONFIG: {:?}, params: {:?}, seed: {:?}",
+                i+1, s, sim_config, params, seed
+            ));
+            if let Some(seed) = seed { seed_py_random(py, seed)?; }
+            let rng = match sim_config.policy_rng {
+                true => Some(PyModule::import(py, "random")?.getattr("Random")?.call1((seed,))?),
+                false => None,
+            };
+
+            let now = std::time::Instant::now(); // Perf. diag.
+            // 2. Create trajectory
+            let outcome = run_single_simulation(
+                py, cadcad_config, params, rng, s, i, &mut recorder, &mut progress, checkpointer.as_ref()
+            );
+            let trajectory = std::mem::take(&mut recorder.trajectory);
+            // Sinks have the states of failed runs too, up to the failure
+            let outcome = match &mut recorder.sink {
+                Some(sink) => outcome.and(sink.end_run()),
+                None => outcome,
+            };
+
+            // 3. Report
+            let mut report = RunReport {
+                subset: s, run: i+1, elapsed: now.elapsed(),
+                steps: recorder.steps, states_recorded: recorder.states_recorded, memory_bytes: 0,
+                stop: recorder.stop.take(),
+                interventions: std::mem::take(&mut recorder.interventions),
+            };
+
+            // Failure isolation, as per the error policy
+            let mut is_kept = cadcad_config.keep_trajectories;
+            let mut failed = None;
+            match outcome {
+                Err(err) if !is_exception(py, &err) => {
+                    log(py, LogLevel::Warning, || format!("--- Simulation {} of param subset {} interrupted: {}", i+1, s, err));
+                    interrupted = Some(err);
+                },
+                Err(err) => {
+                    if cadcad_config.error_policy == ErrorPolicy::Abort { return Err(err); }
+                    log(py, LogLevel::Warning, || format!("--- Simulation {} of param subset {} failed: {}", i+1, s, err));
+                    failed = Some(failed_run(py, recorder.last_state, err)?);
+                    is_kept &= cadcad_config.error_policy == ErrorPolicy::TruncateRun;
+                },
+                Ok(()) => {},
+            }
+            if let (true, Some(state)) = (is_kept, trajectory.last()) {
+                report.memory_bytes = trajectory.len() * state_memory(py, state)?;
+            }
+            log(py, LogLevel::Debug, || format!("--- Report: {:?}", report));
+            let report = report.to_object(py)?;
+            run_reports.push(report.clone_ref(py));
+            if let Some(failed) = &failed { failed_runs.push(failed.clone_ref(py)); }
+
+            let mut kept = None;
+            if is_kept {
+                // 4. Log trajectory
+                if cadcad_config.print_trajectory { print_trajectory(&trajectory); }
+
+                let trajectory_of_state_ptrs = trajectory.iter()
+                    .map(|state| (*state).into())
+                    .collect::<Vec<PyObject>>();
+                kept = Some(trajectory_of_state_ptrs.to_object(py));
+                result_data.push(trajectory_of_state_ptrs);
+            }
+            if interrupted.is_some() { break; }
+            if let Some(checkpointer) = &checkpointer { checkpointer.finish_run(py, s, i, kept, failed, report)?; }
+            if let Some(progress) = &mut progress { progress.runs_done(py, 1)?; }
+        }
+        Ok(())
+    })();
+    let closed = match recorder.sink {
+        Some(sink) => sink.close(),
+        None => Ok(()),
+    };
+    outcome.and(closed)?;
+    log(py, LogLevel::Info, || format!("### End of project: {}", cadcad_config.name));
+
+    Ok(ResultData { trajectories: result_data, failed_runs, run_reports, interrupted })
+}
+
+// Runs the (param subset, run) pairs in a pool of worker processes, each
+// worker running a contiguous slice of them with the same config. Progress
+// is reported as workers are done. When interrupted, the runs not started
+// are cancelled and the results have those of the workers done by then
+fn run_simulation_multi_proc(
+    py: Python,
+    config_args: &PyTuple,
+    runs: &[(usize, usize)],
+    n_workers: usize,
+    sink: Option<&str>,
+    checkpoint: Option<&str>,
+    mut progress: Option<&mut Progress>
+) -> PyResult<ResultData> {
+    let worker_fn = PyModule::import(py, "cadcad_rs")?.getattr("_run_simulation_slice")?;
+    let executor = PyModule::import(py, "concurrent.futures")?
+        .getattr("ProcessPoolExecutor")?
+        .call1((n_workers,))?;
+
+    let chunk_size = ((runs.len() + n_workers - 1) / n_workers).max(1);
+    let result_data = runs.chunks(chunk_size).enumerate().map(|(w, chunk)| {
+        let mut args = vec![worker_fn];
+        args.extend(config_args.iter());
+        // Each worker writes to its own sink and checkpoint files
+        args.push(sink.map(|path| worker_path(path, w)).into_py(py).into_ref(py));
+        args.push(checkpoint.map(|path| worker_path(path, w)).into_py(py).into_ref(py));
+        args.push(chunk.to_vec().into_py(py).into_ref(py));
+        executor.call_method1("submit", PyTuple::new(py, args))
+    }).collect::<PyResult<Vec<_>>>().and_then(|futures| {
+        let mut result_data = ResultData {
+            trajectories: Vec::new(), failed_runs: Vec::new(), run_reports: Vec::new(), interrupted: None
+        };
+        for (w, (future, chunk)) in futures.iter().zip(runs.chunks(chunk_size)).enumerate() {
+            let worker_result_data = match future.call_method0("result") {
+                Ok(worker_result_data) => worker_result_data,
+                // Interrupted, in this process or in the worker
+                Err(err) if !is_exception(py, &err) => {
+                    if let Ok(partial_results) = err.to_object(py).getattr(py, "partial_results") {
+                        result_data.extend(partial_results.as_ref(py))?;
+                    }
+                    for future in &futures[w+1..] {
+                        if !future.call_method0("done")?.is_true()? || future.call_method0("cancelled")?.is_true()? {
+                            continue;
+                        }
+                        match future.call_method0("result") {
+                            Ok(worker_result_data) => result_data.extend(worker_result_data)?,
+                            Err(err) => match err.to_object(py).getattr(py, "partial_results") {
+                                Ok(partial_results) => result_data.extend(partial_results.as_ref(py))?,
+                                Err(_) => continue,
+                            },
+                        }
+                    }
+                    result_data.interrupted = Some(err);
+                    break;
+                },
+                // A SimulationError already tells which run failed
+                Err(err) if err.matches(py, py.get_type::<SimulationError>()) => return Err(err),
+                Err(err) => {
+                    let worker_err = PyRuntimeError::new_err(format!(
+                        "Worker {} running (param subset, run) pairs {:?} failed: {}", w, chunk, err
+                    ));
+                    return Err(with_cause(py, worker_err, err));
+                },
+            };
+            result_data.extend(worker_result_data)?;
+            if let Some(progress) = &mut progress { progress.runs_done(py, chunk.len())?; }
+        }
+        Ok(result_data)
+    });
+    // Without waiting for the runs in progress when interrupted
+    let is_interrupted = result_data.as_ref().map_or(true, |result_data| result_data.interrupted.is_some());
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("wait", !is_interrupted)?;
+    kwargs.set_item("cancel_futures", true)?;
+    executor.call_method("shutdown", (), Some(kwargs))?;
 
     result_data
 }
 
+// ---- cadCAD compatibility ----
+
+// cadcad_rs.compat: cadCAD's API, see compat.py
+const COMPAT: &str = include_str!("compat.py");
+
 // ----------------------------------- pyo3 binding -------------------------------- //
 
 use pyo3::prelude::*;
 use pyo3::types::*;
+use pyo3::exceptions::{PyException, PyKeyError, PyOSError, PyRuntimeError, PyTypeError, PyValueError};
+
+// Raised when a policy/SUF fails, see simulation_error
+pyo3::create_exception!(cadcad_rs, SimulationError, PyException);
+
+// `list` subclass created at module init, so that run outcomes can be
+// attached as attributes to the usual list of trajectories
+fn create_result_data_type(py: Python<'_>) -> PyResult<&PyAny> {
+    let attrs = PyDict::new(py);
+    attrs.set_item("__module__", "cadcad_rs")?;
+    attrs.set_item("__doc__", concat!(
+        "List of trajectories, failed_runs lists the runs that failed, ",
+        "run_reports what every run took"
+    ))?;
+    py.get_type::<PyType>().call1(("ResultData", (py.get_type::<PyList>(),), attrs))
+}
+
+// What run_simulation returns
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResultFormat {
+    List,   // ResultData, a list of trajectories (lists of state dicts)
+    Pandas, // DataFrame with the layout of cadCAD's raw_result
+    Polars,
+}
+
+impl ResultFormat {
+    // "dataframe": a polars DataFrame if polars is installed, else a pandas one
+    fn from_name(py: Python, name: &str) -> PyResult<Self> {
+        match name {
+            "list" => Ok(Self::List),
+            "pandas" => Ok(Self::Pandas),
+            "polars" => Ok(Self::Polars),
+            "dataframe" => match PyModule::import(py, "polars") {
+                Ok(_) => Ok(Self::Polars),
+                Err(_) => Ok(Self::Pandas),
+            },
+            other => Err(PyValueError::new_err(format!(
+                "Unknown result format '{}', expected 'list', 'dataframe', 'pandas' or 'polars'", other
+            ))),
+        }
+    }
+}
+
+impl ResultData {
+    // Adds the runs of a worker's ResultData
+    fn extend(&mut self, worker_result_data: &PyAny) -> PyResult<()> {
+        self.trajectories.extend(worker_result_data.extract::<Vec<Vec<PyObject>>>()?);
+        self.failed_runs.extend(
+            worker_result_data.getattr("failed_runs")?.extract::<Vec<PyObject>>()?
+        );
+        self.run_reports.extend(
+            worker_result_data.getattr("run_reports")?.extract::<Vec<PyObject>>()?
+        );
+        Ok(())
+    }
+
+    // The result, or the error the simulation was interrupted by with the
+    // result as its partial_results attribute
+    fn into_result_or_interrupted(mut self, py: Python, result_format: ResultFormat) -> PyResult<PyObject> {
+        let interrupted = self.interrupted.take();
+        let result = self.into_result(py, result_format)?;
+        match interrupted {
+            Some(err) => {
+                err.to_object(py).setattr(py, "partial_results", result)?;
+                Err(err)
+            },
+            None => Ok(result),
+        }
+    }
+
+    fn into_py_result_data(self, py: Python) -> PyResult<PyObject> {
+        let result_data = PyModule::import(py, "cadcad_rs")?
+            .getattr("ResultData")?
+            .call1((self.trajectories,))?;
+        result_data.setattr("failed_runs", self.failed_runs)?;
+        result_data.setattr("run_reports", self.run_reports)?;
+        Ok(result_data.into())
+    }
+
+    fn into_result(self, py: Python, result_format: ResultFormat) -> PyResult<PyObject> {
+        match result_format {
+            ResultFormat::List => self.into_py_result_data(py),
+            ResultFormat::Pandas => {
+                let columns = self.to_columns(py)?;
+                let df = PyModule::import(py, "pandas")?.getattr("DataFrame")?.call1((columns,))?;
+                // Not as attributes, pandas would take them for columns
+                df.getattr("attrs")?.set_item("failed_runs", self.failed_runs)?;
+                df.getattr("attrs")?.set_item("run_reports", self.run_reports)?;
+                Ok(df.into())
+            },
+            // polars DataFrames have no room for failed_runs and run_reports
+            ResultFormat::Polars => {
+                let columns = self.to_columns(py)?;
+                Ok(PyModule::import(py, "polars")?.getattr("DataFrame")?.call1((columns,))?.into())
+            },
+        }
+    }
+
+    // Columns of cadCAD's raw_result: one per state key (None where a state
+    // has no such key), then simulation, subset, run, substep and timestep
+    fn to_columns(&self, py: Python) -> PyResult<PyObject> {
+        let cadcad_keys = ["simulation", "subset", "run", "substep", "timestep"];
+        let column_of = PyDict::new(py);
+        let mut columns: Vec<(&PyAny, Vec<PyObject>)> = Vec::new();
+        let mut n_row = 0;
+        for state in self.trajectories.iter().flatten() {
+            for (key, value) in state.as_ref(py).downcast::<PyDict>()? {
+                let c = match column_of.get_item(key) {
+                    Some(c) => c.extract::<usize>()?,
+                    None => {
+                        column_of.set_item(key, columns.len())?;
+                        columns.push((key, vec![py.None(); n_row]));
+                        columns.len() - 1
+                    },
+                };
+                columns[c].1.push(value.into());
+            }
+            n_row += 1;
+            for (_, values) in columns.iter_mut() {
+                values.resize(n_row, py.None());
+            }
+        }
+
+        let is_cadcad_key = |key: &PyAny| key.extract::<&str>().map_or(false, |key| cadcad_keys.contains(&key));
+        let data = PyDict::new(py);
+        for (key, values) in columns.iter().filter(|(key, _)| !is_cadcad_key(key)) {
+            data.set_item(key, PyList::new(py, values))?;
+        }
+        data.set_item("simulation", PyList::new(py, vec![0; n_row]))?;
+        for key in &cadcad_keys[1..] {
+            match column_of.get_item(key) {
+                Some(c) => data.set_item(key, PyList::new(py, &columns[c.extract::<usize>()?].1))?,
+                None => data.set_item(key, PyList::new(py, vec![py.None(); n_row]))?,
+            }
+        }
+        Ok(data.into())
+    }
+}
+
+// Checks of the run_simulation options which to_cadcad_config does not see
+fn check_run_options(execution_mode: &str, sink: Option<&str>, checkpoint: Option<&str>, resume: bool) -> PyResult<()> {
+    if !matches!(execution_mode, "single_proc" | "multi_proc") {
+        return Err(PyValueError::new_err(format!(
+            "Unknown execution mode '{}', expected 'single_proc' or 'multi_proc'", execution_mode
+        )));
+    }
+    if checkpoint.is_some() && sink.is_some() {
+        // Sinks cannot take back the states written after a checkpoint
+        return Err(PyValueError::new_err("checkpoint cannot be used with a sink"));
+    }
+    if resume && checkpoint.is_none() {
+        return Err(PyValueError::new_err("resume needs a checkpoint"));
+    }
+    Ok(())
+}
+
+// ---- Simulation and Results classes ----
+
+// A simulation to run, of the run_simulation arguments given as keywords.
+// The config is checked when created: ValueError on the errors validate
+// finds, a UserWarning per warning. results holds those of the last run
+#[pyclass(module = "cadcad_rs")]
+pub struct Simulation {
+    name: String,
+    sim_config: Py<PyDict>,
+    init_state: Py<PyDict>,
+    partial_state_update_blocks: Py<PyList>,
+    options: Py<PyDict>, // The other run_simulation arguments
+    results: std::sync::Mutex<Option<Py<Results>>>, // Set from run_async's thread
+}
+
+#[pymethods]
+impl Simulation {
+    #[new]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        py: Python,
+        sim_config: &PyDict,
+        init_state: &PyDict,
+        partial_state_update_blocks: &PyList,
+        name: Option<String>,
+        print_trajectory: Option<bool>,
+        execution_mode: Option<&str>,
+        n_workers: Option<usize>,
+        error_policy: Option<&str>,
+        signal_aggregation: Option<&PyDict>,
+        sink: Option<&str>,
+        keep_trajectories: Option<bool>,
+        progress: Option<&PyAny>,
+        progress_interval: Option<usize>,
+        checkpoint: Option<&str>,
+        checkpoint_interval: Option<usize>,
+        resume: Option<bool>,
+        interventions: Option<&PyList>
+    ) -> PyResult<Self> {
+        let name = name.unwrap_or_else(|| "simulation".to_string());
+        check_run_options(execution_mode.unwrap_or("single_proc"), sink, checkpoint, resume.unwrap_or(false))?;
+        let cadcad_config = to_cadcad_config(
+            py, name.clone(), sim_config, init_state, partial_state_update_blocks,
+            PyBool::new(py, false), error_policy.unwrap_or("abort"), signal_aggregation
+        )?;
+        for intervention in interventions.into_iter().flatten() {
+            Intervention::from_py(intervention)?;
+        }
+        if let Some(progress) = progress { Progress::check(progress)?; }
+        let (errors, warnings): (Vec<_>, Vec<_>) = validate(&cadcad_config)?.into_iter()
+            .partition(|issue| issue.is_error);
+        if !errors.is_empty() {
+            let errors: Vec<_> = errors.into_iter().map(|issue| issue.problem).collect();
+            return Err(PyValueError::new_err(format!("Simulation '{}' is invalid:\n{}", name, errors.join("\n"))));
+        }
+        let warn = PyModule::import(py, "warnings")?.getattr("warn")?;
+        for issue in warnings {
+            warn.call1((format!("Simulation '{}': {}", name, issue.problem),))?;
+        }
+        let options = PyDict::new(py);
+        options.set_item("print_trajectory", print_trajectory.unwrap_or(false))?;
+        options.set_item("execution_mode", execution_mode)?;
+        options.set_item("n_workers", n_workers)?;
+        options.set_item("error_policy", error_policy)?;
+        options.set_item("signal_aggregation", signal_aggregation)?;
+        options.set_item("sink", sink)?;
+        options.set_item("keep_trajectories", keep_trajectories)?;
+        options.set_item("progress", progress)?;
+        options.set_item("progress_interval", progress_interval)?;
+        options.set_item("checkpoint", checkpoint)?;
+        options.set_item("checkpoint_interval", checkpoint_interval)?;
+        options.set_item("resume", resume)?;
+        options.set_item("interventions", interventions)?;
+        Ok(Simulation {
+            name,
+            sim_config: sim_config.into(),
+            init_state: init_state.into(),
+            partial_state_update_blocks: partial_state_update_blocks.into(),
+            options: options.into(),
+            results: std::sync::Mutex::new(None),
+        })
+    }
+
+    #[getter]
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Results of the last run, None before
+    #[getter]
+    fn results(&self, py: Python) -> PyResult<Option<Py<Results>>> {
+        Ok(self.last_results()?.as_ref().map(|results| results.clone_ref(py)))
+    }
+
+    // Run reports of the last run, None before
+    #[getter]
+    fn reports(&self, py: Python) -> PyResult<Option<PyObject>> {
+        self.results(py)?.map(|results| results.as_ref(py).borrow().reports(py)).transpose()
+    }
+
+    // Problems found by a dry run, see validate
+    fn validate(&self, py: Python) -> PyResult<Vec<String>> {
+        let cadcad_config = to_cadcad_config(
+            py, self.name.clone(), self.sim_config.as_ref(py), self.init_state.as_ref(py),
+            self.partial_state_update_blocks.as_ref(py), PyBool::new(py, false),
+            self.option(py, "error_policy")?.extract::<Option<&str>>()?.unwrap_or("abort"),
+            self.option(py, "signal_aggregation")?.extract()?
+        )?;
+        Ok(validate(&cadcad_config)?.into_iter().map(|issue| issue.problem).collect())
+    }
+
+    // Runs the simulation with run_simulation. When interrupted, the
+    // KeyboardInterrupt's partial_results are Results too
+    fn run(&self, py: Python) -> PyResult<Py<Results>> {
+        let run_simulation = PyModule::import(py, "cadcad_rs")?.getattr("run_simulation")?;
+        let args = (
+            &self.name, &self.sim_config, &self.init_state, &self.partial_state_update_blocks,
+            self.option(py, "print_trajectory")?,
+        );
+        let kwargs = self.options.as_ref(py).copy()?;
+        kwargs.del_item("print_trajectory")?;
+        let results = match run_simulation.call(args, Some(kwargs)) {
+            Ok(result_data) => Py::new(py, Results { result_data: result_data.into() })?,
+            Err(err) => {
+                let err_object = err.to_object(py);
+                if let Ok(result_data) = err_object.getattr(py, "partial_results") {
+                    err_object.setattr(py, "partial_results", Py::new(py, Results { result_data })?)?;
+                }
+                return Err(err);
+            },
+        };
+        *self.last_results()? = Some(results.clone_ref(py));
+        Ok(results)
+    }
+
+    // Runs the simulation in a thread, returns a future of its Results:
+    // an asyncio one if called from a running event loop, else a
+    // concurrent.futures one. Python raises KeyboardInterrupt (Ctrl-C) in
+    // the main thread only, so it does not stop the run, which goes on in
+    // its thread until it ends
+    fn run_async(slf: PyRef<Self>, py: Python) -> PyResult<PyObject> {
+        let executor = PyModule::import(py, "concurrent.futures")?
+            .getattr("ThreadPoolExecutor")?.call1((1,))?;
+        let future = executor.call_method1("submit", (slf.into_py(py).getattr(py, "run")?,))?;
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("wait", false)?;
+        executor.call_method("shutdown", (), Some(kwargs))?;
+        let asyncio = PyModule::import(py, "asyncio")?;
+        match asyncio.call_method0("get_running_loop") {
+            Ok(_) => Ok(asyncio.call_method1("wrap_future", (future,))?.into()),
+            Err(_) => Ok(future.into()),
+        }
+    }
+}
+
+impl Simulation {
+    fn option<'py>(&self, py: Python<'py>, key: &str) -> PyResult<&'py PyAny> {
+        self.options.as_ref(py).get_item(key).ok_or_else(||
+            PyKeyError::new_err(format!("Simulation has no '{}' option", key))
+        )
+    }
+
+    // Poisoned by a panic while run_async's thread held it
+    fn last_results(&self) -> PyResult<std::sync::MutexGuard<Option<Py<Results>>>> {
+        self.results.lock().map_err(|_| PyRuntimeError::new_err("Results of a run that panicked"))
+    }
+}
+
+// Results of a Simulation run, over the ResultData of run_simulation
+#[pyclass(module = "cadcad_rs")]
+pub struct Results {
+    result_data: PyObject,
+}
+
+#[pymethods]
+impl Results {
+    // Trajectories of the runs (lists of states), in (param subset, run) order
+    fn runs(&self, py: Python) -> PyResult<PyObject> {
+        Ok(PyList::new(py, self.result_data.as_ref(py).iter()?.collect::<PyResult<Vec<_>>>()?).into())
+    }
+
+    // Last state of every run
+    fn final_states(&self, py: Python) -> PyResult<Vec<PyObject>> {
+        let mut final_states = Vec::new();
+        for trajectory in self.result_data.as_ref(py).iter()? {
+            let trajectory = trajectory?.downcast::<PyList>()?;
+            if !trajectory.is_empty() { final_states.push(trajectory.call_method1("__getitem__", (-1,))?.into()); }
+        }
+        Ok(final_states)
+    }
+
+    // library: "dataframe" (default, polars if installed, else pandas),
+    // "pandas" or "polars", see ResultFormat
+    fn to_dataframe(&self, py: Python, library: Option<&str>) -> PyResult<PyObject> {
+        let result_format = match ResultFormat::from_name(py, library.unwrap_or("dataframe"))? {
+            ResultFormat::List => return Err(PyValueError::new_err(
+                "Unknown dataframe library 'list', expected 'dataframe', 'pandas' or 'polars'"
+            )),
+            result_format => result_format,
+        };
+        let result_data = self.result_data.as_ref(py);
+        ResultData {
+            trajectories: result_data.extract()?,
+            failed_runs: result_data.getattr("failed_runs")?.extract()?,
+            run_reports: result_data.getattr("run_reports")?.extract()?,
+            interrupted: None,
+        }.into_result(py, result_format)
+    }
+
+    // The ResultData run_simulation returned
+    #[getter]
+    fn result_data(&self, py: Python) -> PyObject {
+        self.result_data.clone_ref(py)
+    }
+
+    // A dict per run, see RunReport
+    #[getter]
+    fn reports(&self, py: Python) -> PyResult<PyObject> {
+        self.result_data.getattr(py, "run_reports")
+    }
+
+    // A dict per failed run, see failed_run
+    #[getter]
+    fn failed_runs(&self, py: Python) -> PyResult<PyObject> {
+        self.result_data.getattr(py, "failed_runs")
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn to_cadcad_config<'a>(
+    py: Python<'a>,
+    name: String,
+    sim_config_py: &'a PyDict,
+    init_state_py: &'a PyDict,
+    partial_state_update_blocks_py: &'a PyList,
+    print_trajectory: &PyBool,
+    error_policy: &str,
+    signal_aggregation: Option<&'a PyDict>
+) -> PyResult<cadCADConfig<'a>> {
+    let mut aggregations = std::collections::HashMap::new();
+    for (key, aggregation) in signal_aggregation.into_iter().flatten() {
+        aggregations.insert(key.extract::<String>()?, Aggregation::from_py(py, aggregation)?);
+    }
+    let sim_config = SimConfig { 
+        n_run: get_usize(sim_config_py, "N")?,
+        timesteps: get_usize(sim_config_py, "T")?,
+        params: match sim_config_py.get_item("M") {
+            Some(params) => params.downcast::<PyDict>()?,
+            None => PyDict::new(py),
+        },
+        seed: match sim_config_py.get_item("seed") {
+            Some(seed) => seed.extract::<Option<u64>>()?,
+            None => None,
+        },
+        policy_rng: match sim_config_py.get_item("policy_rng") {
+            Some(policy_rng) => policy_rng.is_true()?,
+            None => false,
+        },
+        strict_state_keys: match sim_config_py.get_item("strict_state_keys") {
+            Some(strict_state_keys) => strict_state_keys.is_true()?,
+            None => false,
+        },
+        record_stride: match sim_config_py.get_item("record_stride") {
+            Some(record_stride) => record_stride.extract::<usize>()?.max(1),
+            None => 1,
+        },
+        record_keys: match sim_config_py.get_item("record_keys") {
+            Some(record_keys) => record_keys.extract::<Option<Vec<String>>>()?,
+            None => None,
+        },
+        final_state_only: match sim_config_py.get_item("final_state_only") {
+            Some(final_state_only) => final_state_only.is_true()?,
+            None => false,
+        },
+        stop_conditions: match sim_config_py.get_item("stop_conditions") {
+            Some(stop_conditions) => StopCondition::from_py(stop_conditions)?,
+            None => Vec::new(),
+        },
+        steady_state: match sim_config_py.get_item("steady_state") {
+            Some(steady_state) if !steady_state.is_none() => Some(SteadyState::from_py(steady_state)?),
+            _ => None,
+        },
+        cadcad_signatures: match sim_config_py.get_item("cadcad_signatures") {
+            Some(cadcad_signatures) => cadcad_signatures.is_true()?,
+            None => false,
+        },
+        history_window: match sim_config_py.get_item("history_window") {
+            Some(history_window) => history_window.extract::<Option<usize>>()?,
+            None => None,
+        },
+        debug: match sim_config_py.get_item("debug") {
+            Some(debug) => debug.is_true()?,
+            None => false,
+        },
+    };
+    let cadcad_config = cadCADConfig {
+        name,
+        sim_config,
+        init_state: init_state_py,
+        partial_state_update_blocks: to_partial_state_update_blocks(
+            partial_state_update_blocks_py
+        )?,
+        print_trajectory: print_trajectory.is_true(),
+        error_policy: ErrorPolicy::from_name(error_policy)?,
+        signal_aggregation: aggregations,
+        default_aggregation: Aggregation::Reduce {
+            name: "add".to_string(),
+            func: PyModule::import(py, "operator")?.getattr("add")?,
+        },
+        sink: None,
+        keep_trajectories: true,
+        checkpoint: None,
+        interventions: Vec::new(),
+    };
+    check_state_keys(&cadcad_config)?;
+    Ok(cadcad_config)
+}
 
 #[pymodule]
-fn cadcad_rs(_py: Python, m: &PyModule) -> PyResult<()> {
+fn cadcad_rs(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add("SimulationError", py.get_type::<SimulationError>())?;
+    m.add("ResultData", create_result_data_type(py)?)?;
+    let state_history = PyModule::from_code(py, STATE_HISTORY, "state_history.py", "state_history")?
+        .getattr("StateHistory")?;
+    state_history.setattr("__module__", "cadcad_rs")?;
+    m.add("StateHistory", state_history)?;
+    m.add("compat", PyModule::from_code(py, COMPAT, "cadcad_rs/compat.py", "cadcad_rs.compat")?)?;
+    m.add_class::<Simulation>()?;
+    m.add_class::<Results>()?;
 
+    // execution_mode: "single_proc" (default) or "multi_proc"
+    // n_workers: worker processes of "multi_proc" mode (default: CPU count)
+    // error_policy: "abort" (default), "skip_run" or "truncate_run"
+    // signal_aggregation: { signal key: "add" (default), "product", "min",
+    //   "max", "concat", "last" or a (previous, new) -> aggregated callable }
+    // result_format: "list" (default), "dataframe" (polars if installed, else
+    //   pandas), "pandas" or "polars", see ResultFormat
+    // sink: .csv, .jsonl or .parquet file the states are written to, one per
+    //   worker in "multi_proc" mode (e.g. out_0.csv), see StateSink
+    // keep_trajectories: keep the trajectories in the result data (default:
+    //   true without a sink)
+    // progress: a (runs_done, n_runs, timestep, timesteps) callable called
+    //   every progress_interval timesteps (default: 1% of them) and after
+    //   each run, or True for a tqdm progress bar, see Progress
+    // A KeyboardInterrupt stops the simulation, it is raised with the runs
+    // done so far as its partial_results
+    // checkpoint: file the runs are checkpointed to, every checkpoint_interval
+    //   timesteps (default: 10% of them) and when done, one per worker in
+    //   "multi_proc" mode. resume: go on from it (same simulation and number
+    //   of workers), see Checkpointer. Not with a sink
+    // interventions: list of dicts, see Intervention
     #[pyfn(m)]
+    #[allow(clippy::too_many_arguments)]
     fn run_simulation(
+        py: Python,
         name: String,
         sim_config_py: &PyDict,
         init_state_py: &PyDict,
-        policies_py: &PyList,
-        state_update_fns_py: &PyList,
-        print_trajectory: &PyBool
-    ) -> PyResult<Vec::<Vec<PyObject>>> {
-        let sim_config = SimConfig { 
-            n_run: get_usize(sim_config_py, "N"),
-            timesteps: get_usize(sim_config_py, "T")
-        };
-        let cadcad_config = cadCADConfig {
-            name,
-            sim_config,
-            init_state: init_state_py,
-            policies: policies_py,
-            state_update_functions: state_update_fns_py,
-            print_trajectory: print_trajectory.is_true(),
+        partial_state_update_blocks_py: &PyList,
+        print_trajectory: &PyBool,
+        execution_mode: Option<&str>,
+        n_workers: Option<usize>,
+        error_policy: Option<&str>,
+        signal_aggregation: Option<&PyDict>,
+        result_format: Option<&str>,
+        sink: Option<&str>,
+        keep_trajectories: Option<bool>,
+        progress: Option<&PyAny>,
+        progress_interval: Option<usize>,
+        checkpoint: Option<&str>,
+        checkpoint_interval: Option<usize>,
+        resume: Option<bool>,
+        interventions: Option<&PyList>
+    ) -> PyResult<PyObject> {
+        let error_policy = error_policy.unwrap_or("abort");
+        let resume = resume.unwrap_or(false);
+        let execution_mode = execution_mode.unwrap_or("single_proc");
+        check_run_options(execution_mode, sink, checkpoint, resume)?;
+        let result_format = ResultFormat::from_name(py, result_format.unwrap_or("list"))?;
+        let keep_trajectories = keep_trajectories.unwrap_or(sink.is_none());
+        let mut cadcad_config = to_cadcad_config(
+            py, name.clone(), sim_config_py, init_state_py,
+            partial_state_update_blocks_py, print_trajectory, error_policy,
+            signal_aggregation
+        )?;
+        cadcad_config.sink = sink.map(str::to_string);
+        cadcad_config.keep_trajectories = keep_trajectories;
+        let checkpoint_interval = checkpoint_interval
+            .unwrap_or(cadcad_config.sim_config.timesteps / 10)
+            .max(1);
+        cadcad_config.checkpoint = checkpoint.map(|path| CheckpointConfig {
+            path: path.to_string(), interval: checkpoint_interval, resume
+        });
+        cadcad_config.interventions = interventions.into_iter().flatten()
+            .map(Intervention::from_py)
+            .collect::<PyResult<_>>()?;
+        let sim_config = &cadcad_config.sim_config;
+        let runs = all_runs(param_subsets(py, sim_config.params)?.len(), sim_config.n_run);
+        let mut progress = match progress {
+            Some(progress) => Progress::from_py(py, progress, progress_interval, runs.len(), sim_config.timesteps)?,
+            None => None,
         };
 
-        let result_data = run_simulation_impl(&cadcad_config);
-        Ok(result_data)
+        let result_data = match execution_mode {
+            "multi_proc" => {
+                let n_workers = match n_workers {
+                    Some(n_workers) => n_workers.max(1),
+                    None => PyModule::import(py, "os")?.call_method0("cpu_count")?
+                        .extract::<Option<usize>>()?.unwrap_or(1),
+                };
+                let config_args = PyTuple::new(py, &[
+                    name.into_py(py),
+                    sim_config_py.into(),
+                    init_state_py.into(),
+                    partial_state_update_blocks_py.into(),
+                    print_trajectory.into(),
+                    error_policy.into_py(py),
+                    signal_aggregation.into_py(py),
+                    keep_trajectories.into_py(py),
+                    checkpoint_interval.into_py(py),
+                    resume.into_py(py),
+                    interventions.into_py(py),
+                ]);
+                run_simulation_multi_proc(py, config_args, &runs, n_workers, sink, checkpoint, progress.as_mut())
+            },
+            _ => run_simulation_impl(&cadcad_config, &runs, progress.as_mut()),
+        };
+        if let Some(progress) = progress { progress.close(py)?; }
+        result_data?.into_result_or_interrupted(py, result_format)
+    }
+
+    // Problems found by a dry run of the config, see validate
+    #[pyfn(m)]
+    #[pyo3(name = "validate")]
+    fn validate_py(
+        py: Python,
+        sim_config_py: &PyDict,
+        init_state_py: &PyDict,
+        partial_state_update_blocks_py: &PyList,
+        signal_aggregation: Option<&PyDict>,
+        sink: Option<&str>,
+        checkpoint: Option<&str>
+    ) -> PyResult<Vec<String>> {
+        let mut cadcad_config = to_cadcad_config(
+            py, "validate".to_string(), sim_config_py, init_state_py,
+            partial_state_update_blocks_py, PyBool::new(py, false), "abort",
+            signal_aggregation
+        )?;
+        cadcad_config.sink = sink.map(str::to_string);
+        cadcad_config.checkpoint = checkpoint.map(|path| CheckpointConfig {
+            path: path.to_string(), interval: 1, resume: false
+        });
+        Ok(validate(&cadcad_config)?.into_iter().map(|issue| issue.problem).collect())
+    }
+
+    // Worker entry point of "multi_proc" mode, runs only the given
+    // (param subset, run) pairs
+    #[pyfn(m)]
+    #[pyo3(name = "_run_simulation_slice")]
+    #[allow(clippy::too_many_arguments)]
+    fn run_simulation_slice(
+        py: Python,
+        name: String,
+        sim_config_py: &PyDict,
+        init_state_py: &PyDict,
+        partial_state_update_blocks_py: &PyList,
+        print_trajectory: &PyBool,
+        error_policy: &str,
+        signal_aggregation: &PyAny, // None or a dict
+        keep_trajectories: bool,
+        checkpoint_interval: usize,
+        resume: bool,
+        interventions: &PyAny, // None or a list
+        sink: &PyAny, // None or a path
+        checkpoint: &PyAny, // None or a path
+        runs: Vec<(usize, usize)>
+    ) -> PyResult<PyObject> {
+        let mut cadcad_config = to_cadcad_config(
+            py, name, sim_config_py, init_state_py,
+            partial_state_update_blocks_py, print_trajectory, error_policy,
+            signal_aggregation.extract()?
+        )?;
+        cadcad_config.sink = sink.extract()?;
+        cadcad_config.keep_trajectories = keep_trajectories;
+        cadcad_config.checkpoint = checkpoint.extract::<Option<String>>()?.map(|path| CheckpointConfig {
+            path, interval: checkpoint_interval, resume
+        });
+        cadcad_config.interventions = interventions.extract::<Option<&PyList>>()?.into_iter().flatten()
+            .map(Intervention::from_py)
+            .collect::<PyResult<_>>()?;
+        run_simulation_impl(&cadcad_config, &runs, None)?.into_result_or_interrupted(py, ResultFormat::List)
     }
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,47 @@
+from collections import deque
+from itertools import islice
+from types import MappingProxyType
+
+class StateHistory:
+    """States of a run so far: history[t] is the tuple of the (read-only)
+    states of timestep t (the initial state for 0, then one per substep, up
+    to the current substep for the last one). Negative indices and slices
+    work as for a list, only the last timesteps are kept with a
+    history_window"""
+    __slots__ = ("_timesteps", "_len")
+
+    def __init__(self, timesteps, length, window=None):
+        self._timesteps = deque(timesteps, maxlen=None if window is None else window + 1)
+        self._len = length
+
+    def __len__(self):
+        return self._len
+
+    def __getitem__(self, t):
+        if isinstance(t, slice):
+            return [self[u] for u in range(*t.indices(self._len))]
+        t = t + self._len if t < 0 else t
+        first = self._len - len(self._timesteps)
+        if not first <= t < self._len:
+            raise IndexError(
+                f"timestep {t} is not in the state history (timesteps {first} to {self._len - 1})"
+            )
+        return tuple(map(MappingProxyType, self._timesteps[t - first]))
+
+    def __iter__(self):
+        return (tuple(map(MappingProxyType, states)) for states in self._timesteps)
+
+    def __repr__(self):
+        return f"StateHistory({self._len} timesteps, {len(self._timesteps)} kept)"
+
+    def _push(self, state, new_timestep):
+        if new_timestep:
+            self._timesteps.append([state])
+            self._len += 1
+        else:
+            self._timesteps[-1].append(state)
+
+    def _checkpoint(self, since):
+        """The timesteps from since which are kept, and the length"""
+        first = self._len - len(self._timesteps)
+        return [list(states) for states in islice(self._timesteps, max(since - first, 0), None)], self._len
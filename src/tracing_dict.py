@@ -0,0 +1,22 @@
+"""dict recording the keys read from it, and the missing ones, for the
+dry run of validate"""
+
+class TracingDict(dict):
+    def __init__(self, *args):
+        super().__init__(*args)
+        self.read = set()
+        self.missing = set()
+
+    def __getitem__(self, key):
+        self.read.add(key)
+        return super().__getitem__(key)
+
+    def __missing__(self, key):
+        self.missing.add(key)
+        raise KeyError(key)
+
+    def get(self, key, default=None):
+        self.read.add(key)
+        if key not in self:
+            self.missing.add(key)
+        return super().get(key, default)
@@ -0,0 +1,30 @@
+## State keys without a state update fn: carried forward unchanged, or an
+## error with strict_state_keys
+## Run with `maturin develop && pytest tests`
+
+import cadcad_rs, pytest
+
+def update_x(state, signals, params):
+    return ('x', state['x'] + state['step'])
+
+partial_state_update_blocks = [
+    {'policies': [], 'variables': [update_x]},
+    {'policies': [], 'variables': [update_x]},
+]
+
+def run(**sim_config):
+    return cadcad_rs.run_simulation(
+        "carry forward", {'T': 3, 'N': 1, **sim_config}, {'x': 0, 'step': 2},
+        partial_state_update_blocks, False
+    )
+
+def test_keys_without_a_state_update_fn_are_carried_forward():
+    [trajectory] = run()
+    assert [state['x'] for state in trajectory] == [0, 2, 4, 6, 8, 10, 12]
+    assert all(state['step'] == 2 for state in trajectory)
+
+def test_keys_without_a_state_update_fn_are_an_error_in_strict_mode():
+    with pytest.raises(cadcad_rs.SimulationError) as err:
+        run(strict_state_keys=True)
+    assert str(err.value) == "State key 'step' has no state update fn (run 1, timestep 1, substep 1)"
+    assert (err.value.key, err.value.timestep, err.value.substep) == ('step', 1, 1)
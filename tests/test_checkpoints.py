@@ -0,0 +1,72 @@
+## Checkpoints: a simulation interrupted then resumed has the results of an
+## uninterrupted one, each run in progress having its own file until it is
+## done. They cannot be used with a sink
+## Run with `maturin develop && pytest tests`
+
+import cadcad_rs, os, pytest, random
+
+interrupt_at = None # (run, timestep) of the state step raises KeyboardInterrupt on
+
+def step(state, params):
+    if (state['run'], state['timestep']) == interrupt_at:
+        raise KeyboardInterrupt
+    return ('step', random.random())
+
+def update_x(state, signals, params):
+    return ('x', state['x'] + signals['step'])
+
+def step_cadcad(params, substep, state_history, previous_state):
+    return {'step': step(previous_state, params)[1]}
+
+def update_x_cadcad(params, substep, state_history, previous_state, policy_input):
+    return ('x', previous_state['x'] + policy_input['step'])
+
+# x at the end of timestep t-2, in the window of 2 timesteps before t
+def update_x_two_back(params, substep, state_history, previous_state, policy_input):
+    t = len(state_history)
+    return ('x_two_back', state_history[t - 2][-1]['x'] if t >= 2 else None)
+
+models = {
+    'plain': ({}, [{'policies': [step], 'variables': [update_x]}]),
+    'state_history': (
+        {'cadcad_signatures': True, 'history_window': 2},
+        [{'policies': [step_cadcad], 'variables': [update_x_cadcad, update_x_two_back]}]
+    ),
+}
+
+init_state = {'x': 0.0, 'x_two_back': None}
+
+def run(model, **options):
+    sim_config, partial_state_update_blocks = models[model]
+    return cadcad_rs.run_simulation(
+        "checkpoints", {'T': 10, 'N': 3, 'seed': 1, **sim_config}, init_state,
+        partial_state_update_blocks, False, **options
+    )
+
+@pytest.mark.parametrize('model', models)
+def test_resumed_simulations_are_those_never_interrupted(model, tmp_path):
+    global interrupt_at
+    uninterrupted = run(model)
+    checkpoint = str(tmp_path / "sim.ckpt")
+    interrupt_at = (2, 7)
+    try:
+        with pytest.raises(KeyboardInterrupt):
+            run(model, checkpoint=checkpoint, checkpoint_interval=2)
+    finally:
+        interrupt_at = None
+    # Run 1 done, run 2 checkpointed at timestep 6 in its own file
+    assert sorted(os.listdir(tmp_path)) == ["sim.ckpt", "sim.ckpt.run0_1"]
+    assert run(model, checkpoint=checkpoint, checkpoint_interval=2, resume=True) == uninterrupted
+    assert os.listdir(tmp_path) == ["sim.ckpt"]
+    # All its runs are done
+    assert run(model, checkpoint=checkpoint, resume=True) == uninterrupted
+
+def test_checkpoints_cannot_be_used_with_a_sink(tmp_path):
+    sink, checkpoint = str(tmp_path / "states.csv"), str(tmp_path / "sim.ckpt")
+    with pytest.raises(ValueError, match="checkpoint cannot be used with a sink"):
+        run('plain', sink=sink, checkpoint=checkpoint)
+    sim_config, partial_state_update_blocks = models['plain']
+    issues = cadcad_rs.validate(
+        {'T': 10, 'N': 3}, init_state, partial_state_update_blocks, sink=sink, checkpoint=checkpoint
+    )
+    assert "checkpoint cannot be used with a sink" in issues
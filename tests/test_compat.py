@@ -0,0 +1,48 @@
+## cadcad_rs.compat: a cadCAD model run through Executor, with and without
+## a param sweep
+## Run with `maturin develop && pytest tests`
+
+from cadcad_rs.compat.configuration import Experiment
+from cadcad_rs.compat.configuration.utils import config_sim
+from cadcad_rs.compat.engine import ExecutionContext, Executor
+
+def p_growth(params, substep, state_history, previous_state):
+    return {'growth': params.get('rate', 0.5) * previous_state['population']}
+
+def s_population(params, substep, state_history, previous_state, policy_input):
+    return 'population', previous_state['population'] + policy_input['growth']
+
+partial_state_update_blocks = [
+    {
+        'policies': {'growth': p_growth},
+        'variables': {'population': s_population}
+    },
+]
+
+def execute(sim_configs):
+    experiment = Experiment()
+    experiment.append_model(
+        sim_configs=sim_configs,
+        initial_state={'population': 100.0},
+        partial_state_update_blocks=partial_state_update_blocks
+    )
+    return Executor(ExecutionContext(), experiment.configs).execute()
+
+def final_states(raw_result):
+    return [state for state in raw_result if state['timestep'] == max(s['timestep'] for s in raw_result)]
+
+def test_param_sweep():
+    raw_result, tensor_field, sessions = execute(config_sim({'N': 2, 'T': range(3), 'M': {'rate': [0.1, 0.2]}}))
+    assert len(raw_result) == 2 * 2 * (1 + 3)
+    final = final_states(raw_result)
+    assert [(state['subset'], state['run']) for state in final] == [(0, 1), (0, 2), (1, 1), (1, 2)]
+    assert [round(state['population'], 6) for state in final] == [133.1, 133.1, 172.8, 172.8]
+    assert all(state['simulation'] == 0 for state in raw_result)
+    assert [session['subset_id'] for session in sessions] == [0, 1]
+
+def test_no_param_sweep():
+    # A sim config without M, not made by config_sim
+    raw_result, tensor_field, sessions = execute({'N': 1, 'T': range(2)})
+    assert len(raw_result) == 1 + 2
+    assert [state['population'] for state in final_states(raw_result)] == [225.0]
+    assert len(sessions) == 1
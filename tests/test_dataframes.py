@@ -0,0 +1,64 @@
+## DataFrame results: the states of all runs in the layout of cadCAD's
+## raw_result, one column per state key then simulation, subset, run,
+## substep and timestep, with pandas or polars
+## Run with `maturin develop && pytest tests`
+
+import cadcad_rs, pytest
+
+def growth(state, params):
+    return ('growth', params['rate'] * state['population'])
+
+def update_population(state, signals, params):
+    return ('population', state['population'] + signals['growth'])
+
+def run(result_format):
+    return cadcad_rs.run_simulation(
+        "dataframes", {'T': 3, 'N': 2, 'M': {'rate': [0.1, 0.2]}}, {'population': 100.0, 'name': 'preys'},
+        [{'policies': [growth], 'variables': [update_population]}], False, result_format=result_format
+    )
+
+columns = ['population', 'name', 'simulation', 'subset', 'run', 'substep', 'timestep']
+
+def test_pandas():
+    pytest.importorskip('pandas')
+    df = run('pandas')
+    assert list(df.columns) == columns
+    assert len(df) == 2 * 2 * (1 + 3)
+    assert list(df['simulation'].unique()) == [0]
+    assert df.groupby(['subset', 'run']).size().to_dict() == {(0, 1): 4, (0, 2): 4, (1, 1): 4, (1, 2): 4}
+    final = df[df['timestep'] == 3]
+    assert [round(population, 6) for population in final['population']] == [133.1, 133.1, 172.8, 172.8]
+    assert list(df['name'].unique()) == ['preys']
+    assert list(df.attrs['failed_runs']) == [] and len(df.attrs['run_reports']) == 4
+
+def test_polars():
+    pytest.importorskip('polars')
+    df = run('polars')
+    assert df.columns == columns
+    assert df.shape == (2 * 2 * (1 + 3), len(columns))
+    assert df['timestep'].to_list() == [0, 1, 2, 3] * 4
+
+def test_dataframe_of_the_library_installed():
+    pandas = pytest.importorskip('pandas')
+    try:
+        import polars
+        expected = polars.DataFrame
+    except ImportError:
+        expected = pandas.DataFrame
+    assert isinstance(run('dataframe'), expected)
+
+def test_results_to_dataframe():
+    pytest.importorskip('pandas')
+    results = cadcad_rs.Simulation(
+        sim_config={'T': 3, 'N': 2, 'M': {'rate': [0.1, 0.2]}},
+        init_state={'population': 100.0, 'name': 'preys'},
+        partial_state_update_blocks=[{'policies': [growth], 'variables': [update_population]}],
+        name="dataframes",
+    ).run()
+    df = results.to_dataframe('pandas')
+    assert df.equals(run('pandas'))
+    assert len(df.attrs['run_reports']) == 4
+
+def test_unknown_result_format():
+    with pytest.raises(ValueError, match="Unknown result format 'dict'"):
+        run('dict')
@@ -0,0 +1,63 @@
+## Early termination: a run stops at the end of the first timestep a stop
+## condition holds on, or once at steady state, reporting when and why. The
+## results then have runs of different lengths
+## Run with `maturin develop && pytest tests`
+
+import cadcad_rs, pytest
+
+def decline(state, params):
+    return ('decline', params['rate'] * state['population'])
+
+def update_population(state, signals, params):
+    return ('population', state['population'] - signals['decline'])
+
+def extinct(state, params):
+    return state['population'] < 1
+
+def stabilize(state, params):
+    return ('decline', min(10, state['population'] - 50))
+
+def run(policy=decline, **sim_config):
+    return cadcad_rs.run_simulation(
+        "early termination", {'T': 20, 'N': 1, 'M': {'rate': [0.5, 0.9]}, **sim_config}, {'population': 100.0},
+        [{'policies': [policy], 'variables': [update_population]}], False
+    )
+
+def test_stop_conditions():
+    # 100 * 0.5^7 < 1, 100 * (1 - 0.9)^3 < 1
+    for stop_conditions, reason in [([extinct], 'extinct'), ({'preys extinct': extinct}, 'preys extinct')]:
+        results = run(stop_conditions=stop_conditions)
+        assert [len(trajectory) for trajectory in results] == [1 + 7, 1 + 3]
+        assert [(report['stopped_at'], report['stop_reason']) for report in results.run_reports] == [
+            (7, reason), (3, reason)
+        ]
+        assert [report['steps'] for report in results.run_reports] == [7, 3]
+
+def test_runs_go_to_their_last_timestep_without_a_stop():
+    results = run(stop_conditions=[])
+    assert [len(trajectory) for trajectory in results] == [1 + 20, 1 + 20]
+    assert [report['stopped_at'] for report in results.run_reports] == [None, None]
+
+def test_steady_state():
+    # 100, 90, 80, 70, 60, 50, 50, 50: unchanged at timesteps 6 and 7
+    results = run(policy=stabilize, steady_state={'window': 2})
+    assert [(report['stopped_at'], report['stop_reason']) for report in results.run_reports] == [
+        (7, 'steady state'), (7, 'steady state')
+    ]
+    assert [state['population'] for state in results[0]] == [100, 90, 80, 70, 60, 50, 50, 50]
+    # Changes of up to 10 are steady
+    results = run(policy=stabilize, steady_state={'tolerance': 10, 'window': 2, 'keys': ['population']})
+    assert [report['stopped_at'] for report in results.run_reports] == [2, 2]
+
+def test_dataframes_of_runs_of_different_lengths():
+    pytest.importorskip('pandas')
+    df = cadcad_rs.run_simulation(
+        "early termination", {'T': 20, 'N': 1, 'M': {'rate': [0.5, 0.9]}, 'stop_conditions': [extinct]},
+        {'population': 100.0}, [{'policies': [decline], 'variables': [update_population]}], False,
+        result_format='pandas'
+    )
+    assert df.groupby('subset')['timestep'].max().to_dict() == {0: 7, 1: 3}
+
+def test_bad_stop_conditions():
+    with pytest.raises(ValueError, match=r"Stop condition 'x' should be a \(state, params\) -> bool callable, got 3"):
+        run(stop_conditions={'x': 3})
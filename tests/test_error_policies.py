@@ -0,0 +1,48 @@
+## Error policies: a failed run aborts the simulation (abort, the default),
+## or is dropped (skip_run) or kept up to its failure (truncate_run) while
+## the other runs go on, the failed runs being listed with their error
+## Run with `maturin develop && pytest tests`
+
+import cadcad_rs, pytest
+
+def fail_in_run_2(state, params):
+    if (state['run'], state['timestep']) == (2, 2):
+        raise ValueError("run 2 failed")
+    return ('step', 1)
+
+def update_x(state, signals, params):
+    return ('x', state['x'] + signals['step'])
+
+partial_state_update_blocks = [{'policies': [fail_in_run_2], 'variables': [update_x]}]
+
+def run(**options):
+    return cadcad_rs.run_simulation(
+        "error policies", {'T': 4, 'N': 3}, {'x': 0}, partial_state_update_blocks, False, **options
+    )
+
+def xs(results):
+    return [[state['x'] for state in trajectory] for trajectory in results]
+
+def test_abort():
+    for options in [{}, {'error_policy': 'abort'}]:
+        with pytest.raises(cadcad_rs.SimulationError, match="run 2, timestep 3"):
+            run(**options)
+
+def test_skip_run():
+    results = run(error_policy='skip_run')
+    assert xs(results) == [[0, 1, 2, 3, 4], [0, 1, 2, 3, 4]]
+    assert [trajectory[0]['run'] for trajectory in results] == [1, 3]
+    [failed] = results.failed_runs
+    assert (failed['subset'], failed['run'], failed['timestep']) == (0, 2, 2)
+    assert isinstance(failed['error'], cadcad_rs.SimulationError)
+    assert isinstance(failed['error'].__cause__, ValueError)
+    assert len(results.run_reports) == 3
+
+def test_truncate_run():
+    results = run(error_policy='truncate_run')
+    assert xs(results) == [[0, 1, 2, 3, 4], [0, 1, 2], [0, 1, 2, 3, 4]]
+    assert [(failed['run'], failed['timestep']) for failed in results.failed_runs] == [(2, 2)]
+
+def test_unknown_error_policy():
+    with pytest.raises(ValueError, match="Unknown error policy 'ignore'"):
+        run(error_policy='ignore')
@@ -0,0 +1,52 @@
+## Interventions: at a timestep, in a window of timesteps or the first time
+## a predicate holds, set state values or params and switch policies off or
+## on for the rest of the run, each firing being in the run's report
+## Run with `maturin develop && pytest tests`
+
+import cadcad_rs, pytest
+
+def step_by_scale(state, params):
+    return ('step', params['scale'])
+
+# x stays put while step_by_scale is disabled
+def update_x(state, signals, params):
+    return ('x', state['x'] + signals.get('step', 0))
+
+def is_x_at_least_103(state, params):
+    return state['x'] >= 103
+
+interventions = [
+    {'name': "reset", 'at': 2, 'set_state': {'x': 100.0}},
+    {'name': "pause", 'window': (4, 5), 'disable_policies': [step_by_scale]},
+    {'name': "resume", 'at': 6, 'enable_policies': [step_by_scale]},
+    {'name': "slow down", 'when': is_x_at_least_103, 'set_params': {'scale': 0.5}},
+]
+
+init_state = {'x': 0.0}
+
+def run(interventions):
+    return cadcad_rs.run_simulation(
+        "interventions", {'T': 8, 'N': 2, 'M': {'scale': [1.0, 2.0]}}, init_state,
+        [{'policies': [step_by_scale], 'variables': [update_x]}], False, interventions=interventions
+    )
+
+def test_interventions():
+    results = run(interventions)
+    # Scale 1.0 then 2.0: reset at 2, paused at 4 and 5, slowed down from 7 and 4
+    assert [[state['x'] for state in trajectory] for trajectory in results] == [
+        [0, 1, 101, 102, 102, 102, 103, 103.5, 104],
+        [0, 1, 101, 102, 102, 102, 103, 103.5, 104],
+        [0, 2, 102, 104, 104, 104, 104.5, 105, 105.5],
+        [0, 2, 102, 104, 104, 104, 104.5, 105, 105.5],
+    ]
+    assert [list(report['interventions']) for report in results.run_reports[::2]] == [
+        [(2, "reset"), (4, "pause"), (5, "pause"), (6, "resume"), (7, "slow down")],
+        [(2, "reset"), (4, "pause"), (4, "slow down"), (5, "pause"), (6, "resume")],
+    ]
+    assert init_state == {'x': 0.0}
+
+def test_bad_interventions():
+    with pytest.raises(ValueError, match="has no 'name'"):
+        run([{'at': 2}])
+    with pytest.raises(ValueError, match="Intervention 'twice' should have one trigger"):
+        run([{'name': "twice", 'at': 2, 'window': (4, 5)}])
@@ -0,0 +1,42 @@
+## Param sweeps (M): one param subset per item of the lists, lists of a
+## single item and other values are passed as they are, lists of other
+## lengths are an error
+## Run with `maturin develop && pytest tests`
+
+import cadcad_rs, pytest
+
+def record_params(state, signals, params):
+    return ('params', (params['a'], params['b'], params['c']))
+
+partial_state_update_blocks = [
+    {
+        'policies': [],
+        'variables': [record_params]
+    },
+]
+
+def run(M):
+    sim_config = {'T': 1, 'N': 2, 'M': M}
+    init_state = {'params': None}
+    return cadcad_rs.run_simulation("param sweep", sim_config, init_state, partial_state_update_blocks, False)
+
+def test_one_param_subset_per_item_of_the_lists():
+    result_data = run({'a': [1, 2, 3], 'b': [10.0, 20.0, 30.0], 'c': 'scalar'})
+    assert len(result_data) == 3 * 2
+    assert [(t[-1]['subset'], t[-1]['run']) for t in result_data] == [
+        (0, 1), (0, 2), (1, 1), (1, 2), (2, 1), (2, 2)
+    ]
+    assert [t[-1]['params'] for t in result_data[::2]] == [
+        (1, 10.0, 'scalar'),
+        (2, 20.0, 'scalar'),
+        (3, 30.0, 'scalar'),
+    ]
+
+def test_no_list_is_a_single_param_subset():
+    result_data = run({'a': 1, 'b': [2.0], 'c': (3, 4)})
+    assert len(result_data) == 2
+    assert [t[-1]['params'] for t in result_data] == [(1, 2.0, (3, 4))] * 2
+
+def test_lists_of_different_lengths_raise():
+    with pytest.raises(ValueError, match="Param 'b' has 2 values, sweeps should have 1 or 3 values"):
+        run({'a': [1, 2, 3], 'b': [10.0, 20.0], 'c': [5]})
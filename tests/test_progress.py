@@ -0,0 +1,46 @@
+## Progress and Ctrl-C: a progress callback (or tqdm bar) called every
+## progress_interval timesteps and after each run, and a KeyboardInterrupt
+## stopping the simulation promptly, raised with the runs done so far
+## Run with `maturin develop && pytest tests`
+
+import _thread, cadcad_rs, pytest
+
+def step(state, params):
+    return ('step', 1)
+
+def update_x(state, signals, params):
+    return ('x', state['x'] + signals['step'])
+
+def run(**options):
+    return cadcad_rs.run_simulation(
+        "progress", {'T': 5, 'N': 2}, {'x': 0}, [{'policies': [step], 'variables': [update_x]}], False, **options
+    )
+
+def test_progress_callback():
+    calls = []
+    run(progress=lambda *progress: calls.append(progress), progress_interval=2)
+    assert calls == [(0, 2, 2, 5), (0, 2, 4, 5), (1, 2, 0, 5), (1, 2, 2, 5), (1, 2, 4, 5), (2, 2, 0, 5)]
+
+def test_default_progress_interval():
+    calls = []
+    run(progress=lambda *progress: calls.append(progress))
+    # 1% of the timesteps, at least 1
+    assert [timestep for (runs_done, _, timestep, _) in calls if runs_done == 0] == [1, 2, 3, 4]
+
+def test_progress_bar(capsys):
+    pytest.importorskip('tqdm')
+    run(progress=True)
+    assert "10/10" in capsys.readouterr().err
+
+def test_ctrl_c_raises_with_the_runs_done_so_far():
+    def ctrl_c_in_run_2(runs_done, n_runs, timestep, timesteps):
+        if (runs_done, timestep) == (1, 2):
+            _thread.interrupt_main()
+    with pytest.raises(KeyboardInterrupt) as interrupted:
+        run(progress=ctrl_c_in_run_2, progress_interval=1)
+    partial_results = interrupted.value.partial_results
+    assert [trajectory[0]['run'] for trajectory in partial_results] == [1, 2]
+    assert len(partial_results[0]) == 1 + 5
+    # Run 2 is stopped before its end
+    assert len(partial_results[1]) < 1 + 5
+    assert len(partial_results.run_reports) == 2
@@ -0,0 +1,40 @@
+## Recording: the states of every record_stride-th timestep, with only the
+## record_keys state keys (and the cadCAD ones), or only the final state.
+## The runs are the same, only what is kept of them changes
+## Run with `maturin develop && pytest tests`
+
+import cadcad_rs
+
+def step(state, params):
+    return ('step', 1)
+
+def update_x(state, signals, params):
+    return ('x', state['x'] + signals['step'])
+
+def update_y(state, signals, params):
+    return ('y', state['y'] - signals['step'])
+
+partial_state_update_blocks = [{'policies': [step], 'variables': [update_x, update_y]}]
+
+def run(**sim_config):
+    return cadcad_rs.run_simulation(
+        "recording", {'T': 6, 'N': 2, **sim_config}, {'x': 0, 'y': 0}, partial_state_update_blocks, False
+    )
+
+def test_record_stride():
+    results = run(record_stride=2)
+    full = run()
+    for trajectory, full_trajectory in zip(results, full):
+        assert [state['timestep'] for state in trajectory] == [0, 2, 4, 6]
+        assert trajectory == [state for state in full_trajectory if state['timestep'] % 2 == 0]
+    assert [(report['steps'], report['states_recorded']) for report in results.run_reports] == [(6, 4), (6, 4)]
+
+def test_record_keys():
+    for trajectory in run(record_keys=['x']):
+        assert all(set(state) == {'x', 'subset', 'run', 'substep', 'timestep'} for state in trajectory)
+        assert [state['x'] for state in trajectory] == [0, 1, 2, 3, 4, 5, 6]
+
+def test_final_state_only():
+    results = run(final_state_only=True)
+    assert [trajectory for trajectory in results] == [[trajectory[-1]] for trajectory in run()]
+    assert [report['states_recorded'] for report in results.run_reports] == [1, 1]
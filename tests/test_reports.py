@@ -0,0 +1,49 @@
+## Run reports and logging: a report per run with the results, and the
+## human-readable output on the "cadcad_rs" logger instead of stdout
+## Run with `maturin develop && pytest tests`
+
+import cadcad_rs, logging
+
+def step(state, params):
+    if (state['run'], state['timestep']) == (2, 3):
+        raise ValueError("run 2 failed")
+    return ('step', 1)
+
+def update_x(state, signals, params):
+    return ('x', state['x'] + signals['step'])
+
+def run(**options):
+    return cadcad_rs.run_simulation(
+        "reports", {'T': 4, 'N': 2}, {'x': 0}, [{'policies': [step], 'variables': [update_x]}], False,
+        error_policy='skip_run', **options
+    )
+
+def test_run_reports():
+    results = run()
+    assert [(report['subset'], report['run']) for report in results.run_reports] == [(0, 1), (0, 2)]
+    done, failed = results.run_reports
+    assert (done['steps'], done['states_recorded']) == (4, 1 + 4)
+    assert (failed['steps'], failed['states_recorded']) == (2, 1 + 2)
+    assert done['elapsed'] >= 0 and done['memory_bytes'] > 0
+    # Not kept in the results
+    assert failed['memory_bytes'] == 0
+    assert (done['stopped_at'], done['stop_reason'], done['interventions']) == (None, None, [])
+
+def test_output_is_logged_not_printed(caplog, capsys):
+    with caplog.at_level(logging.INFO, logger="cadcad_rs"):
+        results = run()
+    assert capsys.readouterr().out == ""
+    project, failed, end = caplog.records
+    assert (project.levelname, project.getMessage()) == ('INFO', "### Project: reports ...")
+    assert failed.levelname == 'WARNING'
+    assert failed.getMessage().startswith("--- Simulation 2 of param subset 0 failed: ")
+    assert str(results.failed_runs[0]['error']) in failed.getMessage()
+    assert (end.levelname, end.getMessage()) == ('INFO', "### End of project: reports")
+
+def test_debug_logs_are_per_run(caplog):
+    with caplog.at_level(logging.DEBUG, logger="cadcad_rs"):
+        run()
+    debug = [record.getMessage() for record in caplog.records if record.levelname == 'DEBUG']
+    assert [message.split(':')[0] for message in debug] == [
+        "--- Simulation 1 of param subset 0", "--- Report", "--- Simulation 2 of param subset 0", "--- Report",
+    ]
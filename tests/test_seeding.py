@@ -0,0 +1,33 @@
+## Seeded runs: the same master seed gives the same trajectories, whatever
+## the execution mode, and every run gets its own random stream
+## Run with `maturin develop && pytest tests`
+
+import cadcad_rs, random
+
+def noise(state, params):
+    return ('noise', random.uniform(-1.0, 1.0))
+
+def own_noise(state, params, rng):
+    return ('noise', rng.uniform(-1.0, 1.0))
+
+def update_x(state, signals, params):
+    return ('x', state['x'] + signals['noise'])
+
+def run(seed, execution_mode="single_proc", policy=noise, policy_rng=False):
+    sim_config = {'T': 20, 'N': 4, 'M': {'scale': [1, 2]}, 'seed': seed, 'policy_rng': policy_rng}
+    partial_state_update_blocks = [{'policies': [policy], 'variables': [update_x]}]
+    return cadcad_rs.run_simulation(
+        "seeding", sim_config, {'x': 0.0}, partial_state_update_blocks, False,
+        execution_mode=execution_mode, n_workers=3
+    )
+
+def test_same_seed_same_trajectories():
+    for policy, policy_rng in [(noise, False), (own_noise, True)]:
+        single_proc = run(42, policy=policy, policy_rng=policy_rng)
+        assert list(single_proc) == list(run(42, policy=policy, policy_rng=policy_rng))
+        assert list(single_proc) == list(run(42, "multi_proc", policy, policy_rng))
+        assert list(single_proc) != list(run(43, policy=policy, policy_rng=policy_rng))
+
+def test_runs_get_different_streams():
+    final_xs = [trajectory[-1]['x'] for trajectory in run(42)]
+    assert len(set(final_xs)) == len(final_xs)
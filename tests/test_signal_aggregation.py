@@ -0,0 +1,88 @@
+## Signals of the same key from several policies: added by default, or
+## aggregated as per signal_aggregation, whatever policies return them as (a
+## tuple, a dict, a list or tuple of pairs, or None)
+## Run with `maturin develop && pytest tests`
+
+import cadcad_rs, operator, pytest
+
+def one(state, params):
+    return ('signal', 1)
+
+def two(state, params):
+    return ('signal', 2)
+
+def three(state, params):
+    return ('signal', 3)
+
+def a_list(state, params):
+    return ('signal', [4])
+
+def as_dict(state, params):
+    return {'signal': 10, 'other': 1}
+
+def as_list(state, params):
+    return [('signal', 20), ('other', 2)]
+
+def as_tuple_of_pairs(state, params):
+    return (('signal', 30), ('other', 3))
+
+def no_signal(state, params):
+    return None
+
+def twice(state, params):
+    return [('signal', 1), ('signal', 2)]
+
+def update_total(state, signals, params):
+    return ('total', signals['signal'])
+
+def update_signals(state, signals, params):
+    return ('signals', dict(signals))
+
+def total(signal_aggregation, policies=(one, three, two)):
+    partial_state_update_blocks = [{'policies': list(policies), 'variables': [update_total]}]
+    result_data = cadcad_rs.run_simulation(
+        "signal aggregation", {'T': 1, 'N': 1}, {'total': None}, partial_state_update_blocks, False,
+        signal_aggregation=signal_aggregation
+    )
+    return result_data[0][-1]['total']
+
+def signals(signal_aggregation, policies):
+    partial_state_update_blocks = [{'policies': policies, 'variables': [update_signals]}]
+    result_data = cadcad_rs.run_simulation(
+        "signal shapes", {'T': 1, 'N': 1}, {'signals': None}, partial_state_update_blocks, False,
+        signal_aggregation=signal_aggregation
+    )
+    return result_data[0][-1]['signals']
+
+def test_aggregations():
+    assert total(None) == 6
+    assert total({'signal': 'add'}) == 6
+    assert total({'signal': 'product'}) == 6
+    assert total({'signal': 'min'}) == 1
+    assert total({'signal': 'max'}) == 3
+    assert total({'signal': 'last'}) == 2
+    assert total({'signal': lambda previous, new: previous * 10 + new}) == 132
+    assert total({'signal': operator.sub}) == -4
+    assert total({'signal': 'concat'}, [a_list, a_list]) == [4, 4]
+    # Other keys are still added
+    assert total({'other': 'last'}) == 6
+
+def test_policies_returning_several_signals():
+    policies = [as_dict, no_signal, as_list, as_tuple_of_pairs, one]
+    assert signals(None, policies) == {'signal': 61, 'other': 6}
+    assert signals({'signal': 'max'}, policies) == {'signal': 30, 'other': 6}
+    assert signals({'signal': 'last', 'other': 'last'}, policies) == {'signal': 1, 'other': 3}
+    assert signals({'signal': 'last'}, [as_list, as_dict]) == {'signal': 10, 'other': 3}
+    # Signals of the same key from a single policy too
+    assert signals(None, [twice]) == {'signal': 3}
+    assert signals({'signal': 'min'}, [twice, three]) == {'signal': 1}
+    assert signals(None, [no_signal]) == {}
+
+def test_signals_that_cannot_be_aggregated():
+    with pytest.raises(cadcad_rs.SimulationError, match="cannot be aggregated"):
+        total(None, [one, a_list])
+    assert total({'signal': 'last'}, [one, a_list]) == [4]
+
+def test_unknown_aggregation():
+    with pytest.raises(ValueError, match="Unknown signal aggregation 'sum'"):
+        total({'signal': 'sum'})
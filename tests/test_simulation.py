@@ -0,0 +1,89 @@
+## Simulation and Results: keyword construction checking the config (errors
+## raise, warnings are warned), runs, results and reports, and the errors
+## of bad options
+## Run with `maturin develop && pytest tests`
+
+import asyncio, cadcad_rs, pytest, re
+
+def growth(state, params):
+    return ('growth', params['rate'] * state['population'])
+
+def unused(state, params):
+    return ('unused', 1)
+
+def interrupt_at_timestep_3(state, params):
+    if state['timestep'] == 2:
+        raise KeyboardInterrupt
+    return growth(state, params)
+
+def update_population(state, signals, params):
+    return ('population', state['population'] + signals['growth'])
+
+def simulation(policies=(growth,), variables=(update_population,), **options):
+    variables = variables if isinstance(variables, dict) else list(variables)
+    return cadcad_rs.Simulation(
+        sim_config={'T': 3, 'N': 2, 'M': {'rate': [0.1, 0.2]}},
+        init_state={'population': 100.0},
+        partial_state_update_blocks=[{'policies': list(policies), 'variables': variables}],
+        name="growth",
+        **options
+    )
+
+def final_populations(results):
+    return [round(state['population'], 6) for state in results.final_states()]
+
+def test_run():
+    sim = simulation()
+    assert sim.name == "growth"
+    assert sim.results is None and sim.reports is None
+    results = sim.run()
+    assert sim.results is not None
+    runs = results.runs()
+    assert len(runs) == 2 * 2
+    assert all(len(trajectory) == 1 + 3 for trajectory in runs)
+    assert final_populations(results) == [133.1, 133.1, 172.8, 172.8]
+    assert list(results.result_data) == runs
+    assert len(results.reports) == 4 and len(sim.reports) == 4
+    assert list(results.failed_runs) == []
+
+def test_run_async():
+    results = simulation().run_async().result(timeout=60)
+    assert final_populations(results) == [133.1, 133.1, 172.8, 172.8]
+
+def test_run_async_in_an_event_loop():
+    async def run():
+        return await simulation().run_async()
+    assert final_populations(asyncio.run(run())) == [133.1, 133.1, 172.8, 172.8]
+
+def test_interrupted_runs_have_partial_results():
+    with pytest.raises(KeyboardInterrupt) as interrupted:
+        simulation(policies=[interrupt_at_timestep_3]).run()
+    partial_results = interrupted.value.partial_results
+    assert isinstance(partial_results, cadcad_rs.Results)
+    assert [len(trajectory) for trajectory in partial_results.runs()] == [1 + 2]
+
+def test_errors_raise_on_construction():
+    with pytest.raises(ValueError, match=re.escape(
+        "Simulation 'growth' is invalid:\nSeveral state update fns update key 'population' (substep 1)"
+    )):
+        simulation(variables=[update_population, update_population])
+    with pytest.raises(ValueError, match="declared for key 'preys', which is not in init_state"):
+        simulation(variables={'preys': update_population})
+
+def test_warnings_on_construction():
+    with pytest.warns(UserWarning, match=re.escape(
+        "Simulation 'growth': Signal 'unused' is produced but no state update fn consumes it (substep 1)"
+    )):
+        sim = simulation(policies=[growth, unused])
+    assert sim.validate() == ["Signal 'unused' is produced but no state update fn consumes it (substep 1)"]
+    assert len(sim.run().runs()) == 4
+
+def test_bad_options_raise():
+    with pytest.raises(ValueError, match="Progress should be a"):
+        simulation(progress=3)
+    with pytest.raises(ValueError, match="Unknown execution mode 'threads'"):
+        simulation(execution_mode='threads')
+    with pytest.raises(ValueError, match="checkpoint cannot be used with a sink"):
+        simulation(sink="states.csv", checkpoint="simulation.ckpt")
+    with pytest.raises(ValueError, match="Unknown dataframe library 'list'"):
+        simulation().run().to_dataframe('list')
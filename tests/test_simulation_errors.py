@@ -0,0 +1,73 @@
+## SimulationError: failures and bad results of policies and state update
+## fns, raised with the step they happened at, the function and its result,
+## and the original error (with its traceback) as __cause__
+## Run with `maturin develop && pytest tests`
+
+import cadcad_rs, pytest, traceback
+
+def step(state, params):
+    return ('step', 1)
+
+def fail_at_timestep_3(state, params):
+    if state['timestep'] == 3:
+        raise ZeroDivisionError("no step")
+    return ('step', 1)
+
+def not_a_signal(state, params):
+    return 42
+
+def update_x(state, signals, params):
+    return ('x', state['x'] + signals['step'])
+
+def update_x_without_key(state, signals, params):
+    return state['x'] + signals['step']
+
+def read_missing_signal(state, signals, params):
+    return ('x', state['x'] + signals['missing'])
+
+# The functions are in substep 2
+def run(policy=step, state_update_fn=update_x):
+    partial_state_update_blocks = [
+        {'policies': [step], 'variables': [update_x]},
+        {'policies': [policy], 'variables': [state_update_fn]},
+    ]
+    return cadcad_rs.run_simulation(
+        "errors", {'T': 5, 'N': 2}, {'x': 0}, partial_state_update_blocks, False
+    )
+
+def test_failures_of_policies():
+    with pytest.raises(cadcad_rs.SimulationError) as err:
+        run(policy=fail_at_timestep_3)
+    assert str(err.value).startswith("Policy 'fail_at_timestep_3' failed (run 1, timestep 3, substep 2)")
+    assert str(err.value).endswith("no step")
+    assert (err.value.subset, err.value.run, err.value.timestep, err.value.substep) == (0, 1, 3, 2)
+    assert (err.value.function, err.value.returned) == ('fail_at_timestep_3', None)
+    cause = err.value.__cause__
+    assert isinstance(cause, ZeroDivisionError)
+    # The traceback goes down to the line which raised
+    assert traceback.extract_tb(cause.__traceback__)[-1].name == 'fail_at_timestep_3'
+
+def test_bad_results_of_policies():
+    with pytest.raises(cadcad_rs.SimulationError) as err:
+        run(policy=not_a_signal)
+    assert str(err.value).startswith(
+        "Policy 'not_a_signal' returned 42, expected a (key, value) tuple, a dict, a list of (key, value) "
+        "tuples or None (run 1, timestep 1, substep 2)"
+    )
+    assert (err.value.function, err.value.returned) == ('not_a_signal', 42)
+    assert isinstance(err.value.__cause__, TypeError)
+
+def test_failures_of_state_update_fns():
+    with pytest.raises(cadcad_rs.SimulationError) as err:
+        run(state_update_fn=read_missing_signal)
+    assert str(err.value).startswith("State update fn 'read_missing_signal' failed (run 1, timestep 1, substep 2)")
+    assert isinstance(err.value.__cause__, KeyError)
+
+def test_bad_results_of_state_update_fns():
+    with pytest.raises(cadcad_rs.SimulationError) as err:
+        run(state_update_fn=update_x_without_key)
+    assert str(err.value).startswith(
+        "State update fn 'update_x_without_key' returned 2, expected a (key, value) tuple "
+        "(run 1, timestep 1, substep 2)"
+    )
+    assert (err.value.timestep, err.value.substep, err.value.returned) == (1, 2, 2)
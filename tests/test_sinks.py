@@ -0,0 +1,44 @@
+## Sinks: the states of runs written to a file as they are produced, the
+## file closed with the states so far when a run aborts the simulation
+## Run with `maturin develop && pytest tests`
+
+import cadcad_rs, csv, json, pytest
+
+def fail_at_timestep_3(state, params):
+    if state['timestep'] == 2:
+        raise ValueError("failed")
+    return ('step', 1)
+
+def update_x(state, signals, params):
+    return ('x', state['x'] + signals['step'])
+
+partial_state_update_blocks = [{'policies': [fail_at_timestep_3], 'variables': [update_x]}]
+
+def run(sink, error_policy="abort"):
+    return cadcad_rs.run_simulation(
+        "sinks", {'T': 5, 'N': 2}, {'x': 0}, partial_state_update_blocks, False,
+        sink=str(sink), error_policy=error_policy
+    )
+
+def test_csv_sink_of_an_aborted_simulation(tmp_path):
+    sink = tmp_path / "states.csv"
+    with pytest.raises(cadcad_rs.SimulationError):
+        run(sink)
+    with open(sink, newline="") as file:
+        rows = list(csv.DictReader(file))
+    assert [(row['run'], row['timestep'], row['x']) for row in rows] == [('1', '0', '0'), ('1', '1', '1'), ('1', '2', '2')]
+
+def test_jsonl_sink_of_an_aborted_simulation(tmp_path):
+    sink = tmp_path / "states.jsonl"
+    with pytest.raises(cadcad_rs.SimulationError):
+        run(sink)
+    with open(sink) as file:
+        states = [json.loads(line) for line in file]
+    assert [state['x'] for state in states] == [0, 1, 2]
+
+def test_sink_of_skipped_runs(tmp_path):
+    sink = tmp_path / "states.jsonl"
+    run(sink, error_policy="skip_run")
+    with open(sink) as file:
+        states = [json.loads(line) for line in file]
+    assert [(state['run'], state['x']) for state in states] == [(1, 0), (1, 1), (1, 2), (2, 0), (2, 1), (2, 2)]
@@ -0,0 +1,54 @@
+## State history (cadcad_signatures): with a history_window, only the last
+## timesteps are in it, and the states of the timesteps which fell out of
+## it keep their values
+## Run with `maturin develop && pytest tests`
+
+import cadcad_rs
+
+# (view, copy of its values when kept) of the states of state_history
+kept_states = []
+
+def update_x(params, substep, state_history, previous_state, policy_input):
+    return ('x', previous_state['x'] + 1)
+
+# x at the end of timestep t-2, in the window of 2 timesteps before t
+def update_x_two_back(params, substep, state_history, previous_state, policy_input):
+    t = len(state_history) - (substep > 1) # Timestep t is in it after its first substep
+    if t < 2:
+        return ('x_two_back', None)
+    for state in state_history[t - 2]:
+        kept_states.append((state, dict(state)))
+    return ('x_two_back', state_history[t - 2][-1]['x'])
+
+partial_state_update_blocks = [
+    {'policies': [], 'variables': [update_x, update_x_two_back]},
+    {'policies': [], 'variables': [update_x, update_x_two_back]},
+]
+
+def run(history_window, record_keys):
+    sim_config = {
+        'T': 20, 'N': 1, 'cadcad_signatures': True, 'history_window': history_window,
+        'record_keys': record_keys
+    }
+    init_state = {'x': 0, 'x_two_back': None}
+    return cadcad_rs.run_simulation(
+        "state history", sim_config, init_state, partial_state_update_blocks, False
+    )
+
+def test_states_in_the_window_keep_their_values():
+    for record_keys in [['x', 'x_two_back'], None]:
+        [trajectory] = run(2, record_keys)
+        assert len(trajectory) == 1 + 20 * 2
+        for state in trajectory[1:]:
+            expected = 2 * (state['timestep'] - 2) if state['timestep'] >= 2 else None
+            assert state['x_two_back'] == expected
+        [whole_history] = run(None, record_keys)
+        assert list(trajectory) == list(whole_history)
+
+def test_states_out_of_the_window_keep_their_values():
+    kept_states.clear()
+    for record_keys in [['x'], None]:
+        run(2, record_keys)
+    assert len(kept_states) > 0
+    for state, values in kept_states:
+        assert dict(state) == values
@@ -0,0 +1,45 @@
+## State keys of state update fns: declared with a dict of variables (as in
+## cadCAD), they must be in init_state and be the keys of the updates.
+## validate also reports several updates of a key in a substep
+## Run with `maturin develop && pytest tests`
+
+import cadcad_rs, pytest
+
+def update_x(state, signals, params):
+    return ('x', state['x'] + 1)
+
+def update_y(state, signals, params):
+    return ('y', state['x'] + 1)
+
+def run(variables):
+    partial_state_update_blocks = [{'policies': [], 'variables': variables}]
+    return cadcad_rs.run_simulation(
+        "state keys", {'T': 3, 'N': 1}, {'x': 0}, partial_state_update_blocks, False
+    )
+
+def test_declared_keys():
+    [trajectory] = run({'x': update_x})
+    assert [state['x'] for state in trajectory] == [0, 1, 2, 3]
+
+def test_declared_keys_not_in_init_state():
+    with pytest.raises(
+        ValueError, match="State update fn of substep 1 declared for key 'y', which is not in init_state"
+    ):
+        run({'x': update_x, 'y': update_y})
+
+def test_updates_of_another_key_than_the_declared_one():
+    with pytest.raises(cadcad_rs.SimulationError) as err:
+        run({'x': update_y})
+    assert str(err.value) == (
+        "State update fn 'update_y' declared for key 'x' returned an update of key 'y' "
+        "(run 1, timestep 1, substep 1)"
+    )
+    assert (err.value.declared, err.value.key, err.value.timestep, err.value.substep) == ('x', 'y', 1, 1)
+
+def test_several_updates_of_a_key():
+    partial_state_update_blocks = [{'policies': [], 'variables': [update_x, update_x, update_y]}]
+    issues = cadcad_rs.validate({'T': 3, 'N': 1}, {'x': 0}, partial_state_update_blocks)
+    assert issues == [
+        "Several state update fns update key 'x' (substep 1)",
+        "State update fn 'update_y' updates key 'y', which is not in init_state (substep 1)",
+    ]
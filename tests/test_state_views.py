@@ -0,0 +1,54 @@
+## State views: the Python functions get read-only views of the states, and
+## in debug mode those mutating a value of a state (which the views cannot
+## prevent) fail the run
+## Run with `maturin develop && pytest tests`
+
+import cadcad_rs, pytest
+
+def write_x(state, params):
+    state['x'] = 10
+    return ('step', 1)
+
+def append_to_log(state, params):
+    state['log'].append(state['timestep'])
+    return ('step', 1)
+
+def step(state, params):
+    return ('step', 1)
+
+def update_x(state, signals, params):
+    return ('x', state['x'] + signals['step'])
+
+def run(policy, debug=False, init_state=None):
+    partial_state_update_blocks = [{'policies': [policy], 'variables': [update_x]}]
+    init_state = init_state if init_state is not None else {'x': 0, 'log': []}
+    return cadcad_rs.run_simulation(
+        "state views", {'T': 3, 'N': 1, 'debug': debug}, init_state, partial_state_update_blocks, False
+    )
+
+def test_writing_through_a_state_view_raises():
+    with pytest.raises(cadcad_rs.SimulationError, match="Policy 'write_x' failed") as err:
+        run(write_x)
+    assert isinstance(err.value.__cause__, TypeError)
+
+def test_mutations_are_detected_in_debug_mode():
+    with pytest.raises(cadcad_rs.SimulationError) as err:
+        run(append_to_log, debug=True)
+    assert str(err.value) == (
+        "Policy 'append_to_log' mutated state key(s) 'log', states are read-only "
+        "(run 1, timestep 1, substep 1)"
+    )
+    assert (err.value.function, err.value.keys) == ('append_to_log', ['log'])
+
+def test_mutations_are_not_checked_by_default():
+    [trajectory] = run(append_to_log)
+    assert [state['x'] for state in trajectory] == [0, 1, 2, 3]
+
+def test_debug_mode_runs_functions_which_do_not_mutate():
+    [trajectory] = run(step, debug=True)
+    assert [state['x'] for state in trajectory] == [0, 1, 2, 3]
+
+def test_init_state_is_left_unchanged():
+    init_state = {'x': 0, 'log': []}
+    run(step, init_state=init_state)
+    assert init_state == {'x': 0, 'log': []}